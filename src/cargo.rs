@@ -0,0 +1,207 @@
+use crate::{edit, Result};
+use serde::Deserialize;
+use std::{collections::BTreeSet, path::PathBuf, process::Command};
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "reason")]
+pub(crate) enum Line {
+    #[serde(rename = "compiler-message")]
+    CompilerMessage { message: Message },
+
+    #[serde(other)]
+    Other,
+}
+
+/// A message from the compiler, as reported by `cargo check
+/// --message-format json`.
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct Message {
+    pub message: String,
+    /// How severe the message is, such as `error` or `warning`.
+    pub level: String,
+    pub code: Option<Code>,
+    pub spans: Vec<Span>,
+    /// Notes and help messages attached to this message.
+    #[serde(default)]
+    pub children: Vec<Message>,
+    /// The message as the compiler would print it.
+    #[serde(default)]
+    pub rendered: Option<String>,
+}
+
+impl Message {
+    /// The spans of this message and all of its children.
+    pub fn all_spans(&self) -> impl Iterator<Item = &Span> {
+        self.spans
+            .iter()
+            .chain(self.children.iter().flat_map(|c| &c.spans))
+    }
+
+    /// The span that the message is mainly about, if the message
+    /// points to any code.
+    pub fn primary_span(&self) -> Option<&Span> {
+        self.spans.iter().find(|s| s.is_primary)
+    }
+
+    /// The replacements suggested by the compiler's help messages
+    /// for exactly the same code as `span`.
+    pub(crate) fn suggestions_for<'a>(&'a self, span: &'a Span) -> impl Iterator<Item = &'a str> {
+        self.children
+            .iter()
+            .flat_map(|c| &c.spans)
+            .filter(move |s| {
+                s.file_name == span.file_name
+                    && s.byte_start == span.byte_start
+                    && s.byte_end == span.byte_end
+            })
+            .flat_map(|s| s.suggested_replacement.as_deref())
+    }
+
+    /// The spans of every machine-applicable suggestion, along with
+    /// the index of the suggestion they belong to. All of the spans of
+    /// a suggestion need to be applied together.
+    pub(crate) fn machine_applicable_suggestions(&self) -> impl Iterator<Item = (usize, &Span)> {
+        self.children.iter().enumerate().flat_map(|(i, c)| {
+            c.spans
+                .iter()
+                .filter(|s| s.suggested_replacement.is_some())
+                .filter(|s| s.suggestion_applicability == Some(Applicability::MachineApplicable))
+                .map(move |s| (i, s))
+        })
+    }
+}
+
+/// An error code, such as `E0425`.
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct Code {
+    pub code: String,
+}
+
+/// A range of a file that a compiler message refers to.
+#[derive(Debug, Deserialize, PartialOrd, Ord, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Span {
+    pub byte_start: usize,
+    pub byte_end: usize,
+    /// 1-based
+    pub line_start: usize,
+    /// 1-based
+    pub line_end: usize,
+    /// 1-based, in characters
+    pub column_start: usize,
+    /// 1-based, in characters
+    pub column_end: usize,
+    /// Relative to the workspace root
+    pub file_name: String,
+    pub is_primary: bool,
+    pub text: Vec<Text>,
+    pub suggested_replacement: Option<String>,
+    pub suggestion_applicability: Option<Applicability>,
+}
+
+impl Span {
+    /// Whether `content` still contains the source code that the
+    /// compiler saw at this span. The file may have been changed
+    /// since it was compiled.
+    pub(crate) fn matches(&self, content: &str) -> bool {
+        let mut lines = content.lines().skip(self.line_start.saturating_sub(1));
+        let same_lines = self.text.iter().all(|t| lines.next() == Some(&t.text));
+
+        same_lines
+            && edit::check_range(content, (self.byte_start, self.byte_end)).is_ok()
+            && edit::line_column(content, self.byte_start) == (self.line_start, self.column_start)
+            && edit::line_column(content, self.byte_end) == (self.line_end, self.column_end)
+    }
+
+    /// The byte range of the file covered by this span.
+    pub(crate) fn range(&self) -> (usize, usize) {
+        (self.byte_start, self.byte_end)
+    }
+
+    /// The source code covered by a span that starts and ends on the
+    /// same line.
+    pub(crate) fn highlighted(&self) -> Option<&str> {
+        if self.line_start != self.line_end {
+            return None;
+        }
+
+        let line = &self.text.first()?.text;
+        let byte = |column: usize| {
+            line.char_indices()
+                .map(|(i, _)| i)
+                .chain(Some(line.len()))
+                .nth(column.checked_sub(1)?)
+        };
+        line.get(byte(self.column_start)?..byte(self.column_end)?)
+    }
+}
+
+/// How confident the compiler is in a suggested replacement.
+#[derive(Debug, Deserialize, PartialOrd, Ord, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Applicability {
+    MachineApplicable,
+    #[serde(other)]
+    Other,
+}
+
+/// One complete line of the source code covered by a span.
+#[derive(Debug, Deserialize, PartialOrd, Ord, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Text {
+    pub text: String,
+}
+
+/// Finds the root directory of the Cargo workspace containing the
+/// current directory.
+pub fn workspace_root() -> Result<PathBuf> {
+    let metadata = metadata(Command::new("cargo"))?;
+
+    Ok(metadata.workspace_root.into())
+}
+
+/// Runs `cargo metadata` using the provided `cargo` command.
+pub(crate) fn metadata(mut cargo: Command) -> Result<Metadata> {
+    let output = cargo
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "Could not read the Cargo metadata: {}",
+            String::from_utf8_lossy(&output.stderr).trim(),
+        )
+        .into());
+    }
+    let metadata = serde_json::from_slice(&output.stdout)?;
+
+    Ok(metadata)
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Metadata {
+    pub(crate) workspace_root: String,
+    pub(crate) target_directory: String,
+    /// The members of the workspace.
+    #[serde(default)]
+    pub(crate) packages: Vec<Package>,
+}
+
+impl Metadata {
+    /// The manifest of the workspace root and of every member.
+    pub(crate) fn manifests(&self) -> BTreeSet<PathBuf> {
+        let root = PathBuf::from(&self.workspace_root).join("Cargo.toml");
+        let members = self
+            .packages
+            .iter()
+            .map(|p| PathBuf::from(&p.manifest_path));
+
+        Some(root).into_iter().chain(members).collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Package {
+    pub(crate) manifest_path: String,
+}
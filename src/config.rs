@@ -0,0 +1,279 @@
+use crate::Result;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Options shared by everyone working on a project.
+///
+/// The configuration is read from `snafu-upgrade.toml` in the
+/// workspace root, or from the `snafu-upgrade` table of the
+/// `[workspace.metadata]` or `[package.metadata]` section of the root
+/// `Cargo.toml`. Paths are relative to the workspace root.
+///
+/// ```toml
+/// suffix = "Snafu"
+/// type-suffix = { "db::Error" = "Context" }
+/// extra-check-arg = ["--all-features"]
+/// max-iterations = 10
+/// rules = ["upgrade-rules.toml"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+#[non_exhaustive]
+pub struct Config {
+    pub suffix: Option<String>,
+    /// Context selector suffixes for specific error enums, keyed by
+    /// the path of the type within its crate.
+    pub type_suffix: Option<BTreeMap<String, String>>,
+    pub preserve_names: Option<bool>,
+    pub deprecated_aliases: Option<bool>,
+    pub extra_check_arg: Option<Vec<String>>,
+    pub directory: Option<PathBuf>,
+    pub max_iterations: Option<usize>,
+    pub apply_compiler_suggestions: Option<bool>,
+    pub rollback_on_failure: Option<bool>,
+    pub commit_each_iteration: Option<bool>,
+    pub branch: Option<String>,
+    pub upgrade_dependency: Option<bool>,
+    /// Files containing extra [`Rule`](crate::Rule)s.
+    pub rules: Option<Vec<PathBuf>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Manifest {
+    package: Section,
+    workspace: Section,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Section {
+    metadata: Metadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Metadata {
+    #[serde(rename = "snafu-upgrade")]
+    snafu_upgrade: Option<Config>,
+}
+
+impl Config {
+    pub const FILE_NAME: &'static str = "snafu-upgrade.toml";
+
+    /// Finds the configuration for the workspace at `workspace_root`,
+    /// if there is one. The paths in the configuration are made
+    /// absolute.
+    pub fn load(workspace_root: &Path) -> Result<Option<Self>> {
+        let mut found = Vec::new();
+
+        let path = workspace_root.join(Self::FILE_NAME);
+        if let Some(content) = read_if_exists(&path)? {
+            let config = toml::from_str(&content)
+                .map_err(|e| format!("Could not load {}: {}", path.display(), e))?;
+            found.push((path.display().to_string(), config));
+        }
+
+        let manifest_path = workspace_root.join("Cargo.toml");
+        if let Some(content) = read_if_exists(&manifest_path)? {
+            let manifest: Manifest = toml::from_str(&content)
+                .map_err(|e| format!("Could not load {}: {}", manifest_path.display(), e))?;
+
+            let sections = [
+                ("workspace", manifest.workspace),
+                ("package", manifest.package),
+            ];
+            for (name, section) in sections {
+                if let Some(config) = section.metadata.snafu_upgrade {
+                    let location = format!(
+                        "[{}.metadata.snafu-upgrade] in {}",
+                        name,
+                        manifest_path.display()
+                    );
+                    found.push((location, config));
+                }
+            }
+        }
+
+        if found.len() > 1 {
+            let locations: Vec<_> = found.into_iter().map(|(l, _)| l).collect();
+            return Err(format!(
+                "Found more than one configuration; only one may be used: {}",
+                locations.join(", "),
+            )
+            .into());
+        }
+
+        Ok(found
+            .pop()
+            .map(|(_, config)| config.relative_to(workspace_root)))
+    }
+
+    fn relative_to(mut self, root: &Path) -> Self {
+        self.directory = self.directory.map(|d| root.join(d));
+        self.rules = self
+            .rules
+            .map(|rules| rules.into_iter().map(|r| root.join(r)).collect());
+        self
+    }
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    /// A workspace root in the temporary directory that is deleted
+    /// when dropped.
+    struct Workspace(PathBuf);
+
+    impl Workspace {
+        fn new(name: &str) -> Self {
+            let root = env::temp_dir().join(format!(
+                "snafu-upgrade-assistant-config-{}-{}",
+                name,
+                process::id()
+            ));
+            fs::create_dir_all(&root).unwrap();
+            Self(root)
+        }
+
+        fn write(&self, path: &str, content: &str) -> &Self {
+            fs::write(self.0.join(path), content).unwrap();
+            self
+        }
+
+        fn load(&self) -> Result<Option<Config>> {
+            Config::load(&self.0)
+        }
+    }
+
+    impl Drop for Workspace {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const MANIFEST: &str = r#"
+[package]
+name = "example"
+version = "0.1.0"
+"#;
+
+    #[test]
+    fn no_configuration() {
+        let workspace = Workspace::new("none");
+        workspace.write("Cargo.toml", MANIFEST);
+
+        assert_eq!(workspace.load().unwrap(), None);
+    }
+
+    #[test]
+    fn configuration_file() {
+        let workspace = Workspace::new("file");
+        workspace.write("Cargo.toml", MANIFEST).write(
+            Config::FILE_NAME,
+            r#"
+suffix = "Snafu"
+preserve-names = false
+directory = "src"
+rules = ["upgrade-rules.toml"]
+"#,
+        );
+
+        let config = workspace.load().unwrap().unwrap();
+
+        assert_eq!(config.suffix.as_deref(), Some("Snafu"));
+        assert_eq!(config.preserve_names, Some(false));
+        assert_eq!(config.deprecated_aliases, None);
+        assert_eq!(config.directory, Some(workspace.0.join("src")));
+        assert_eq!(
+            config.rules,
+            Some(vec![workspace.0.join("upgrade-rules.toml")]),
+        );
+    }
+
+    #[test]
+    fn package_metadata() {
+        let workspace = Workspace::new("package");
+        workspace.write(
+            "Cargo.toml",
+            &format!(
+                "{}\n[package.metadata.snafu-upgrade]\nmax-iterations = 3\n",
+                MANIFEST
+            ),
+        );
+
+        let config = workspace.load().unwrap().unwrap();
+
+        assert_eq!(config.max_iterations, Some(3));
+    }
+
+    #[test]
+    fn file_and_metadata_are_rejected() {
+        let workspace = Workspace::new("file-and-metadata");
+        workspace
+            .write(
+                "Cargo.toml",
+                "[workspace]\n\n[workspace.metadata.snafu-upgrade]\nsuffix = \"Snafu\"\n",
+            )
+            .write(Config::FILE_NAME, "suffix = \"Context\"\n");
+
+        let error = workspace.load().unwrap_err().to_string();
+
+        assert!(error.contains("more than one configuration"), "{}", error);
+        assert!(error.contains(Config::FILE_NAME), "{}", error);
+        assert!(
+            error.contains("[workspace.metadata.snafu-upgrade]"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn workspace_and_package_metadata_are_rejected() {
+        let workspace = Workspace::new("workspace-and-package");
+        workspace.write(
+            "Cargo.toml",
+            &format!(
+                "{}\n[package.metadata.snafu-upgrade]\n\n[workspace.metadata.snafu-upgrade]\n",
+                MANIFEST
+            ),
+        );
+
+        let error = workspace.load().unwrap_err().to_string();
+
+        assert!(
+            error.contains("[workspace.metadata.snafu-upgrade]"),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("[package.metadata.snafu-upgrade]"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn unknown_options_are_rejected() {
+        let workspace = Workspace::new("unknown");
+        workspace.write(Config::FILE_NAME, "sufix = \"Snafu\"\n");
+
+        let error = workspace.load().unwrap_err().to_string();
+
+        assert!(error.contains("sufix"), "{}", error);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::cargo;
+use serde::Serialize;
+use std::fmt;
+
+/// A message reported by the compiler.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Diagnostic {
+    /// How severe the message is, such as `error` or `warning`.
+    pub level: String,
+    pub code: Option<String>,
+    pub message: String,
+    pub location: Option<Location>,
+    /// The message as the compiler would print it, including the
+    /// source code and any notes.
+    pub rendered: Option<String>,
+}
+
+/// A range of a file reported by the compiler.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Location {
+    /// The file name, relative to the workspace root.
+    pub file: String,
+    pub start: usize,
+    pub end: usize,
+    /// The 1-based line of `start`.
+    pub line: usize,
+    /// The 1-based column of `start`, in characters.
+    pub column: usize,
+    /// The 1-based line of `end`.
+    pub end_line: usize,
+    /// The 1-based column of `end`, in characters.
+    pub end_column: usize,
+}
+
+impl Diagnostic {
+    /// Suggests how to fix errors with a code that is commonly caused
+    /// by upgrading SNAFU.
+    pub fn hint(&self) -> Option<&'static str> {
+        Some(match self.code.as_deref()? {
+            "E0412" | "E0422" | "E0423" | "E0425" | "E0432" | "E0574" => {
+                "context selectors now end with `Snafu`; a selector that was not renamed \
+                 may belong to an error type outside of this workspace"
+            }
+            "E0433" => "items such as `Backtrace` may need to be imported from `snafu` directly",
+            "E0593" => "closures passed to `with_context` now take the error as an argument",
+            "E0599" => {
+                "methods such as `context`, `fail`, and `build` come from SNAFU's \
+                 extension traits; check that `snafu::prelude::*` is imported"
+            }
+            "E0277" => {
+                "a context selector field or source may need an explicit conversion, \
+                 such as `#[snafu(source(from(...)))]`"
+            }
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(location) = &self.location {
+            write!(
+                f,
+                "{}:{}:{}: ",
+                location.file, location.line, location.column,
+            )?;
+        }
+        write!(f, "{}", self.level)?;
+        if let Some(code) = &self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl From<&cargo::Message> for Diagnostic {
+    fn from(message: &cargo::Message) -> Self {
+        Self {
+            level: message.level.clone(),
+            code: message.code.as_ref().map(|c| c.code.clone()),
+            message: message.message.clone(),
+            location: message.primary_span().map(Location::from),
+            rendered: message.rendered.clone(),
+        }
+    }
+}
+
+impl From<&cargo::Span> for Location {
+    fn from(span: &cargo::Span) -> Self {
+        Self {
+            file: span.file_name.clone(),
+            start: span.byte_start,
+            end: span.byte_end,
+            line: span.line_start,
+            column: span.column_start,
+            end_line: span.line_end,
+            end_column: span.column_end,
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use crate::{Category, Kind, Result};
+use std::collections::BTreeMap;
+
+/// The replacements the compiler suggested for each byte range of a
+/// file.
+#[derive(Debug, Default)]
+pub(crate) struct Suggestions<'a> {
+    /// Replacements suggested by the compiler's help messages for
+    /// exactly the code a fixer changes, such as names similar to a
+    /// missing context selector.
+    pub(crate) names: BTreeMap<(usize, usize), Vec<&'a str>>,
+
+    /// Machine-applicable replacements, along with an identifier of
+    /// the complete suggestion they belong to.
+    pub(crate) machine_applicable: BTreeMap<(usize, usize), (SuggestionId, &'a str)>,
+}
+
+/// The index of the compiler message and the index of the suggestion
+/// inside of that message.
+pub(crate) type SuggestionId = (usize, usize);
+
+/// Replaces the bytes `start..end` of a file with `replacement`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edit {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+impl Edit {
+    /// The text that this edit replaces.
+    pub fn original<'a>(&self, content: &'a str) -> &'a str {
+        &content[self.start..self.end]
+    }
+
+    /// Whether both edits change the same part of the file. Two
+    /// insertions at the same position overlap, as the order they
+    /// should be applied in is unknown.
+    pub fn overlaps(&self, other: &Edit) -> bool {
+        (self.start < other.end && other.start < self.end) || self.start == other.start
+    }
+}
+
+/// The planned edits for a file, along with the edits that could not
+/// be applied because they conflict with another edit.
+#[derive(Debug, Default)]
+pub(crate) struct Plan {
+    /// Sorted by position, with no two edits overlapping.
+    pub(crate) edits: Vec<Category<Edit>>,
+
+    /// Each skipped edit, along with the planned edit it conflicts
+    /// with.
+    pub(crate) conflicts: Vec<(Category<Edit>, Category<Edit>)>,
+}
+
+impl Plan {
+    /// Adds `edit` unless an identical edit is already planned. If it
+    /// overlaps with a planned edit, it is recorded as a conflict
+    /// instead.
+    fn insert(&mut self, edit: Category<Edit>) {
+        if self.contains(&edit.value) {
+            return;
+        }
+
+        match self.overlapping(&edit.value) {
+            Some(planned) => {
+                let planned = planned.clone();
+                self.conflicts.push((edit, planned));
+            }
+            None => {
+                let index = self
+                    .edits
+                    .partition_point(|planned| planned.value < edit.value);
+                self.edits.insert(index, edit);
+            }
+        }
+    }
+
+    /// Whether an edit with the same range and replacement is planned.
+    fn contains(&self, edit: &Edit) -> bool {
+        self.edits.iter().any(|planned| planned.value == *edit)
+    }
+
+    fn overlapping(&self, edit: &Edit) -> Option<&Category<Edit>> {
+        self.edits
+            .iter()
+            .find(|planned| planned.value.overlaps(edit))
+    }
+}
+
+/// Checks that the byte range `start..end` is inside of `content` and
+/// does not split a character, so that it is safe to slice.
+pub(crate) fn check_range(content: &str, (start, end): (usize, usize)) -> Result<()> {
+    if start > end || end > content.len() {
+        let (line, column) = line_column(content, content.len());
+
+        return Err(format!(
+            "{}:{}: bytes {}..{} are outside of the file, which is {} bytes long; \
+             the file may have changed since it was compiled",
+            line,
+            column,
+            start,
+            end,
+            content.len(),
+        )
+        .into());
+    }
+
+    if !content.is_char_boundary(start) || !content.is_char_boundary(end) {
+        let boundary = (0..=start)
+            .rev()
+            .find(|&i| content.is_char_boundary(i))
+            .unwrap_or(0);
+        let (line, column) = line_column(content, boundary);
+
+        return Err(format!(
+            "{}:{}: bytes {}..{} do not start and end on a character boundary; \
+             the file may have changed since it was compiled",
+            line, column, start, end,
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Combines the edits from every fixer with the compiler's suggested
+/// edits for the byte `ranges` of a file.
+///
+/// The same edit may be produced for multiple compiler messages, so
+/// identical edits are only applied once. When fixer edits overlap,
+/// the one closest to the start of the file is used. Compiler
+/// suggestions are only used when none of their edits overlap with a
+/// fixer edit or an earlier suggestion.
+pub(crate) fn plan(
+    mut fixer_edits: Vec<Category<Edit>>,
+    ranges: &[(usize, usize)],
+    suggestions: &Suggestions<'_>,
+) -> Plan {
+    fixer_edits.sort();
+
+    let mut plan = Plan::default();
+    for edit in fixer_edits {
+        plan.insert(edit);
+    }
+
+    let mut compiler_suggestions = BTreeMap::<_, Vec<_>>::new();
+    for range in ranges {
+        if let Some(&(id, replacement)) = suggestions.machine_applicable.get(range) {
+            compiler_suggestions.entry(id).or_default().push(Edit {
+                start: range.0,
+                end: range.1,
+                replacement: replacement.to_owned(),
+            });
+        }
+    }
+
+    for (_id, mut edits) in compiler_suggestions {
+        edits.retain(|e| !plan.contains(e));
+
+        let conflict = edits.iter().find_map(|e| plan.overlapping(e)).cloned();
+
+        let edits = edits
+            .into_iter()
+            .map(|e| Category::new(Kind::COMPILER_SUGGESTION, e));
+        match conflict {
+            Some(planned) => plan.conflicts.extend(edits.map(|e| (e, planned.clone()))),
+            None => edits.for_each(|e| plan.insert(e)),
+        }
+    }
+
+    plan
+}
+
+/// The 1-based line and character column of the byte `offset`.
+pub(crate) fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let head = &content[..offset];
+    let line_start = head.rfind('\n').map_or(0, |i| i + 1);
+
+    let line = head.matches('\n').count() + 1;
+    let column = head[line_start..].chars().count() + 1;
+
+    (line, column)
+}
+
+/// Applies every edit to `content`. Nothing is changed if any edit
+/// would split a character or overlaps with another edit.
+pub(crate) fn apply(content: &str, edits: &[Category<Edit>]) -> Result<String> {
+    let mut edits: Vec<_> = edits.iter().map(|e| &e.value).collect();
+    edits.sort();
+
+    for edit in &edits {
+        check_range(content, (edit.start, edit.end))?;
+    }
+    for pair in edits.windows(2) {
+        if pair[0].overlaps(pair[1]) {
+            let (line, column) = line_column(content, pair[1].start);
+            return Err(format!(
+                "{}:{}: refusing to apply edits that overlap with each other",
+                line, column,
+            )
+            .into());
+        }
+    }
+
+    let mut content = content;
+    let mut pieces = Vec::new();
+
+    for edit in edits.into_iter().rev() {
+        let (head, tail) = content.split_at(edit.end);
+        pieces.push(tail);
+        pieces.push(&edit.replacement);
+        content = &head[..edit.start];
+    }
+    pieces.push(content);
+
+    Ok(pieces.into_iter().rev().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIX: Kind = Kind::new("test", "test");
+
+    fn edit(start: usize, end: usize, replacement: &str) -> Category<Edit> {
+        Category::new(
+            FIX,
+            Edit {
+                start,
+                end,
+                replacement: replacement.to_owned(),
+            },
+        )
+    }
+
+    #[test]
+    fn plan_applies_identical_edits_once() {
+        let plan = plan(
+            vec![edit(4, 7, "Baz"), edit(4, 7, "Baz")],
+            &[],
+            &Suggestions::default(),
+        );
+
+        assert_eq!(plan.edits, [edit(4, 7, "Baz")]);
+        assert!(plan.conflicts.is_empty());
+    }
+
+    #[test]
+    fn plan_uses_the_first_of_overlapping_edits() {
+        let plan = plan(
+            vec![edit(6, 10, "later"), edit(4, 8, "earlier")],
+            &[],
+            &Suggestions::default(),
+        );
+
+        assert_eq!(plan.edits, [edit(4, 8, "earlier")]);
+        assert_eq!(
+            plan.conflicts,
+            [(edit(6, 10, "later"), edit(4, 8, "earlier"))],
+        );
+    }
+
+    #[test]
+    fn plan_keeps_adjacent_edits() {
+        let plan = plan(
+            vec![edit(4, 8, "b"), edit(0, 4, "a")],
+            &[],
+            &Suggestions::default(),
+        );
+
+        assert_eq!(plan.edits, [edit(0, 4, "a"), edit(4, 8, "b")]);
+        assert!(plan.conflicts.is_empty());
+    }
+
+    #[test]
+    fn plan_rejects_two_insertions_at_the_same_offset() {
+        let plan = plan(
+            vec![edit(3, 3, "first"), edit(3, 3, "second")],
+            &[],
+            &Suggestions::default(),
+        );
+
+        assert_eq!(plan.edits, [edit(3, 3, "first")]);
+        assert_eq!(
+            plan.conflicts,
+            [(edit(3, 3, "second"), edit(3, 3, "first"))]
+        );
+    }
+
+    #[test]
+    fn plan_skips_compiler_suggestions_that_overlap_a_fix() {
+        let mut suggestions = Suggestions::default();
+        suggestions
+            .machine_applicable
+            .insert((0, 3), ((0, 0), "one"));
+        suggestions
+            .machine_applicable
+            .insert((6, 9), ((0, 0), "two"));
+
+        let plan = plan(vec![edit(5, 7, "fix")], &[(0, 3), (6, 9)], &suggestions);
+
+        let suggested = |start, end, replacement: &str| {
+            let mut edit = edit(start, end, replacement);
+            edit.kind = Kind::COMPILER_SUGGESTION;
+            edit
+        };
+        assert_eq!(plan.edits, [edit(5, 7, "fix")]);
+        assert_eq!(
+            plan.conflicts,
+            [
+                (suggested(0, 3, "one"), edit(5, 7, "fix")),
+                (suggested(6, 9, "two"), edit(5, 7, "fix")),
+            ],
+        );
+    }
+
+    #[test]
+    fn apply_replaces_each_range() {
+        let modified = apply("let foo = bar;", &[edit(10, 13, "baz"), edit(4, 7, "x")]);
+
+        assert_eq!(modified.unwrap(), "let x = baz;");
+    }
+
+    #[test]
+    fn apply_refuses_two_insertions_at_the_same_offset() {
+        let error = apply("ab", &[edit(1, 1, "2"), edit(1, 1, "1")]).unwrap_err();
+
+        assert!(error.to_string().contains("overlap"), "{}", error);
+    }
+
+    #[test]
+    fn apply_refuses_overlapping_edits() {
+        let error = apply("let foo = bar;", &[edit(4, 9, "x"), edit(8, 13, "y")]).unwrap_err();
+
+        assert!(error.to_string().contains("overlap"), "{}", error);
+    }
+
+    #[test]
+    fn line_column_counts_characters() {
+        let content = "é = 1;\n  ü + ß";
+
+        assert_eq!(line_column(content, 0), (1, 1));
+        assert_eq!(line_column(content, "é".len()), (1, 2));
+        assert_eq!(
+            line_column(content, content.find('\n').unwrap() + 1),
+            (2, 1)
+        );
+        assert_eq!(line_column(content, content.find('+').unwrap()), (2, 5));
+        assert_eq!(line_column(content, content.len()), (2, 8));
+    }
+
+    #[test]
+    fn check_range_accepts_character_boundaries() {
+        let content = "let é = \"ü\";";
+        let start = content.find('é').unwrap();
+
+        assert!(check_range(content, (start, start + 'é'.len_utf8())).is_ok());
+        assert!(check_range(content, (0, content.len())).is_ok());
+        assert!(check_range(content, (start, start)).is_ok());
+    }
+
+    #[test]
+    fn check_range_rejects_split_characters() {
+        let content = "a\nlet é = 1;";
+        let start = content.find('é').unwrap();
+
+        let error = check_range(content, (start + 1, start + 2)).unwrap_err();
+        assert!(error.to_string().starts_with("2:5: "), "{}", error);
+
+        let error = check_range(content, (start, start + 1)).unwrap_err();
+        assert!(
+            error.to_string().contains("character boundary"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn check_range_rejects_ranges_outside_of_the_file() {
+        let content = "ü\nß";
+
+        let error = check_range(content, (0, content.len() + 1)).unwrap_err();
+        assert!(error.to_string().starts_with("2:2: "), "{}", error);
+
+        assert!(check_range(content, (3, 2)).is_err());
+    }
+
+    #[test]
+    fn apply_refuses_to_split_a_character() {
+        let error = apply("é", &[edit(1, 2, "e")]).unwrap_err();
+
+        assert!(
+            error.to_string().contains("character boundary"),
+            "{}",
+            error
+        );
+    }
+}
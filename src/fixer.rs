@@ -0,0 +1,315 @@
+use crate::{
+    cargo::{Message, Span},
+    edit::{line_column, Edit},
+    ErrorType, Result, Scan,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::{borrow::Cow, collections::BTreeMap, fmt, path::Path, sync::Arc};
+
+/// Identifies a kind of fix.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Kind {
+    /// Identifies the kind in machine-readable output, such as
+    /// `context_selector_rename`.
+    pub id: Cow<'static, str>,
+
+    /// A short, human-readable description, such as "context selector
+    /// rename".
+    pub name: Cow<'static, str>,
+}
+
+impl Kind {
+    /// The compiler suggested a machine-applicable fix.
+    pub const COMPILER_SUGGESTION: Self = Self::new("compiler_suggestion", "compiler suggestion");
+
+    /// An error type was given its own context selector suffix.
+    pub const SUFFIX_ATTRIBUTE: Self = Self::new("suffix_attribute", "suffix attribute");
+
+    /// A renamed context selector kept its old name as a deprecated
+    /// alias.
+    pub const SELECTOR_ALIAS: Self = Self::new("selector_alias", "deprecated selector alias");
+
+    pub const fn new(id: &'static str, name: &'static str) -> Self {
+        Self {
+            id: Cow::Borrowed(id),
+            name: Cow::Borrowed(name),
+        }
+    }
+}
+
+/// Finds the compiler messages that a kind of fix applies to and
+/// decides how to change the code they point to.
+///
+/// Add a fixer to a [`Migrator`](crate::Migrator) to handle changes
+/// beyond the built-in ones.
+pub trait Fixer: fmt::Debug + Send + Sync {
+    /// The kind of fix that this fixer makes. Each fixer should have a
+    /// different kind.
+    fn kind(&self) -> Kind;
+
+    /// The spans of `message` that point to code this fixer changes.
+    fn spans<'a>(&self, message: &'a Message) -> Vec<&'a Span>;
+
+    /// Decides how to change the code at the byte `range`.
+    fn fix(&self, context: &Context<'_>, range: (usize, usize)) -> Result<Fix>;
+
+    /// The error types whose context selectors the code at `span`
+    /// refers to. These are given a suffix attribute when they need
+    /// one.
+    fn error_types<'s>(&self, span: &Span, scan: &'s Scan) -> Vec<&'s ErrorType> {
+        let _ = (span, scan);
+        Vec::new()
+    }
+}
+
+/// What a [`Fixer`] knows about the code being fixed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Context<'a> {
+    /// The compiler message that pointed to the code.
+    pub message: &'a Message,
+
+    /// The file being fixed, relative to the workspace root.
+    pub file: &'a Path,
+
+    /// The current content of the file.
+    pub content: &'a str,
+
+    /// What context selector suffix to use.
+    pub suffix: &'a str,
+
+    /// What context selector suffix to use for specific error types.
+    pub type_suffixes: &'a BTreeMap<String, String>,
+
+    /// Every SNAFU error type in the workspace.
+    pub scan: &'a Scan,
+
+    /// Replacements that the compiler suggested for exactly the code
+    /// being fixed.
+    pub suggestions: &'a [&'a str],
+}
+
+impl Context<'_> {
+    /// The names SNAFU 0.7 may generate for the context selector
+    /// `name`, without any module path. This is more than one name
+    /// only when several error types with different suffixes have a
+    /// selector with the same name.
+    pub fn upgraded_selectors(&self, name: &str) -> Vec<String> {
+        let selector = name.rsplit("::").next().unwrap_or(name).trim();
+        let upgrade = |t: &ErrorType| {
+            let suffix = self
+                .type_suffixes
+                .get(&t.path)
+                .map_or(self.suffix, String::as_str);
+            t.upgraded_selector(selector, suffix)
+        };
+
+        let mut names: Vec<_> = self
+            .scan
+            .types_with_selector(self.file, name)
+            .into_iter()
+            .map(upgrade)
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+}
+
+/// How a [`Fixer`] decided to change the code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fix {
+    Edit(Edit),
+
+    /// The code already looks correct.
+    Unchanged,
+
+    /// The code is not something this fixer should change after all.
+    /// The compiler's message is left for the person running the
+    /// assistant.
+    Unrelated,
+}
+
+/// The fixers for upgrading from SNAFU 0.6 to 0.7.
+pub(crate) fn builtin() -> Vec<Arc<dyn Fixer>> {
+    vec![
+        Arc::new(ContextSelectorRename),
+        Arc::new(WithContextArgument),
+        Arc::new(EqualSyntax),
+    ]
+}
+
+/// A context selector needs the new suffix.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ContextSelectorRename;
+
+impl ContextSelectorRename {
+    const ERROR_CODES: &'static [&'static str] =
+        &["E0412", "E0422", "E0423", "E0425", "E0432", "E0574"];
+}
+
+impl Fixer for ContextSelectorRename {
+    fn kind(&self) -> Kind {
+        Kind::new("context_selector_rename", "context selector rename")
+    }
+
+    fn spans<'a>(&self, message: &'a Message) -> Vec<&'a Span> {
+        if !has_code(message, Self::ERROR_CODES) {
+            return Vec::new();
+        }
+
+        message.spans.iter().filter(|s| s.is_primary).collect()
+    }
+
+    fn fix(&self, context: &Context<'_>, (start, end): (usize, usize)) -> Result<Fix> {
+        let name = &context.content[start..end];
+
+        if !context.scan.is_selector(name) {
+            return Ok(Fix::Unrelated);
+        }
+
+        let is_path = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || "_:#".contains(c) || c.is_whitespace());
+        if !is_path {
+            return unexpected(context.content, (start, end), "a name");
+        }
+
+        let upgraded = context.upgraded_selectors(name);
+        let selector = name.rsplit("::").next().unwrap_or(name).trim_start();
+
+        // The name is already the one SNAFU 0.7 generates, such as
+        // when the suffix is empty
+        if upgraded.iter().any(|u| u == selector) {
+            return Ok(Fix::Unchanged);
+        }
+
+        // Prefer the compiler's idea of the new name, when it has one
+        let suggestion = context.suggestions.iter().find(|s| {
+            let selector = s.rsplit("::").next().unwrap_or(s);
+            upgraded.iter().any(|u| u == selector)
+        });
+
+        let replacement = match (suggestion, upgraded.first()) {
+            (Some(suggestion), _) => suggestion.to_string(),
+            (None, Some(upgraded)) => {
+                format!("{}{}", &name[..name.len() - selector.len()], upgraded)
+            }
+            (None, None) => return Ok(Fix::Unrelated),
+        };
+
+        Ok(Fix::Edit(Edit {
+            start,
+            end,
+            replacement,
+        }))
+    }
+
+    fn error_types<'s>(&self, span: &Span, scan: &'s Scan) -> Vec<&'s ErrorType> {
+        span.highlighted().map_or_else(Vec::new, |name| {
+            scan.types_with_selector(Path::new(&span.file_name), name)
+        })
+    }
+}
+
+/// A `with_context` closure needs to accept an argument.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct WithContextArgument;
+
+impl Fixer for WithContextArgument {
+    fn kind(&self) -> Kind {
+        Kind::new("with_context_argument", "with_context argument")
+    }
+
+    fn spans<'a>(&self, message: &'a Message) -> Vec<&'a Span> {
+        if !has_code(message, &["E0593"]) {
+            return Vec::new();
+        }
+
+        // The secondary span points to the closure arguments
+        message.spans.iter().filter(|s| !s.is_primary).collect()
+    }
+
+    fn fix(&self, context: &Context<'_>, (start, end): (usize, usize)) -> Result<Fix> {
+        if &context.content[start..end] != "||" {
+            return unexpected(context.content, (start, end), "`||`");
+        }
+
+        Ok(Fix::Edit(Edit {
+            start,
+            end,
+            replacement: "|_|".into(),
+        }))
+    }
+}
+
+/// A `snafu(...)` attribute uses the removed `=` syntax.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct EqualSyntax;
+
+impl Fixer for EqualSyntax {
+    fn kind(&self) -> Kind {
+        Kind::new("equal_syntax", "attribute syntax")
+    }
+
+    fn spans<'a>(&self, message: &'a Message) -> Vec<&'a Span> {
+        static LOOKS_LIKE_ATTRIBUTE_ERROR: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r#"snafu\(.*="#).unwrap());
+
+        let is_attribute_equal = message.code.is_none()
+            && message.spans.iter().any(|s| {
+                s.text
+                    .iter()
+                    .any(|t| LOOKS_LIKE_ATTRIBUTE_ERROR.is_match(&t.text))
+            });
+
+        if !is_attribute_equal {
+            return Vec::new();
+        }
+
+        message.spans.iter().filter(|s| s.is_primary).collect()
+    }
+
+    fn fix(&self, context: &Context<'_>, (start, _end): (usize, usize)) -> Result<Fix> {
+        let content = context.content;
+
+        // Skim backwards to find spaces
+        let start = content[..start].trim_end().len();
+
+        static REWRITE_EQUAL_ATTRIBUTE: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r#"\s*=\s*"([^"]+)""#).unwrap());
+
+        let cap = match REWRITE_EQUAL_ATTRIBUTE.captures(&content[start..]) {
+            Some(cap) => cap,
+            None => return Ok(Fix::Unchanged),
+        };
+        let whole = cap.get(0).unwrap();
+
+        Ok(Fix::Edit(Edit {
+            start: start + whole.start(),
+            end: start + whole.end(),
+            replacement: format!(r#"({})"#, cap.get(1).unwrap().as_str()),
+        }))
+    }
+}
+
+fn has_code(message: &Message, error_codes: &[&str]) -> bool {
+    message
+        .code
+        .as_ref()
+        .is_some_and(|c| error_codes.contains(&c.code.as_str()))
+}
+
+fn unexpected(content: &str, (start, end): (usize, usize), expected: &str) -> Result<Fix> {
+    let (line, column) = line_column(content, start);
+    Err(format!(
+        "{}:{}: expected {} but found `{}`; the file may have changed since it was compiled",
+        line,
+        column,
+        expected,
+        &content[start..end],
+    )
+    .into())
+}
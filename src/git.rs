@@ -0,0 +1,142 @@
+use crate::Result;
+use std::{
+    collections::BTreeSet,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// A git repository, accessed using the `git` command.
+#[derive(Debug, Clone)]
+pub(crate) struct Repository {
+    root: PathBuf,
+}
+
+impl Repository {
+    /// Finds the repository containing `dir`, if any.
+    pub(crate) fn discover(dir: &Path) -> Result<Option<Self>> {
+        let output = match Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+        {
+            Ok(output) => output,
+            // git is not installed, so there can't be a repository
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        let root = String::from_utf8(output.stdout)?;
+        Ok(Some(Self {
+            root: root.trim_end().into(),
+        }))
+    }
+
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether there are any uncommitted changes or untracked files.
+    pub(crate) fn is_dirty(&self) -> Result<bool> {
+        let output = self.run(&["status", "--porcelain"])?;
+        Ok(!output.trim().is_empty())
+    }
+
+    pub(crate) fn create_branch(&self, name: &str) -> Result<()> {
+        self.run(&["checkout", "-b", name])?;
+        Ok(())
+    }
+
+    /// Commits the current content of `paths`, ignoring any other
+    /// changes. Paths that git ignores, such as an untracked
+    /// `Cargo.lock`, are skipped.
+    pub(crate) fn commit(&self, paths: &[&Path], message: &str) -> Result<()> {
+        let ignored = self.ignored(paths)?;
+        let paths: Vec<_> = paths.iter().filter(|p| !ignored.contains(**p)).collect();
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let mut add = self.command();
+        add.args(["add", "--"]).args(&paths);
+        Self::check(add)?;
+
+        let mut commit = self.command();
+        commit
+            .args(["commit", "--quiet", "-m", message, "--"])
+            .args(&paths);
+        Self::check(commit)?;
+
+        Ok(())
+    }
+
+    /// The `paths` that git ignores. Tracked files are never ignored.
+    fn ignored(&self, paths: &[&Path]) -> Result<BTreeSet<PathBuf>> {
+        let mut command = self.command();
+        command
+            .args(["check-ignore", "--stdin", "-z"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = command.spawn()?;
+
+        // Separating the paths with NUL avoids git quoting them
+        let mut stdin = child.stdin.take().ok_or("Could not write to git")?;
+        for path in paths {
+            stdin.write_all(path.to_string_lossy().as_bytes())?;
+            stdin.write_all(b"\0")?;
+        }
+        drop(stdin);
+
+        let output = child.wait_with_output()?;
+
+        // Exits with 1 when none of the paths are ignored
+        if !matches!(output.status.code(), Some(0 | 1)) {
+            return Err(format!(
+                "{:?} failed: {}",
+                command,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            )
+            .into());
+        }
+
+        let stdout = String::from_utf8(output.stdout)?;
+        Ok(stdout
+            .split('\0')
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.root);
+        command
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String> {
+        let mut command = self.command();
+        command.args(args);
+        Self::check(command)
+    }
+
+    fn check(mut command: Command) -> Result<String> {
+        let output = command.output()?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "{:?} failed: {}",
+                command,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            )
+            .into());
+        }
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{semver::Renamed, Category, Edit, ErrorType, Kind, Options, Result, Scan};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// The code added next to error types, along with the suffix each
+/// error type's context selectors were given.
+#[derive(Debug, Default)]
+pub(crate) struct Insertions<'a> {
+    /// The suffix for each error type that does not use the default,
+    /// keyed by the error type's path.
+    pub(crate) type_suffixes: BTreeMap<String, String>,
+
+    /// The reported error types whose context selectors are renamed.
+    pub(crate) renamed: Vec<Renamed<'a>>,
+
+    /// The edits for each file, keyed by the file name relative to the
+    /// workspace root.
+    pub(crate) edits: BTreeMap<String, Vec<Category<Edit>>>,
+}
+
+impl Insertions<'_> {
+    fn insert(
+        &mut self,
+        workspace_root: &Path,
+        error_type: &ErrorType,
+        kind: Kind,
+        code: &dyn Code,
+    ) -> Result<()> {
+        let file_name = error_type.file.to_string_lossy().into_owned();
+        let content = fs::read_to_string(workspace_root.join(&error_type.file))?;
+        let offset = code.offset(error_type);
+
+        let edit = Edit {
+            start: offset,
+            end: offset,
+            replacement: code.code(&content, offset),
+        };
+        self.edits
+            .entry(file_name)
+            .or_default()
+            .push(Category::new(kind, edit));
+
+        Ok(())
+    }
+}
+
+/// Code added next to an error type.
+trait Code {
+    /// Where the code is inserted.
+    fn offset(&self, error_type: &ErrorType) -> usize;
+
+    /// The code to insert at `offset`, matching the indentation of the
+    /// surrounding code.
+    fn code(&self, content: &str, offset: usize) -> String;
+}
+
+/// A `#[snafu(context(suffix(...)))]` attribute. An empty suffix keeps
+/// the variant names.
+struct SuffixAttribute<'a>(&'a str);
+
+impl Code for SuffixAttribute<'_> {
+    fn offset(&self, error_type: &ErrorType) -> usize {
+        error_type.attribute_offset
+    }
+
+    fn code(&self, content: &str, offset: usize) -> String {
+        let suffix = if self.0.is_empty() { "false" } else { self.0 };
+        let attribute = format!("#[snafu(context(suffix({})))]", suffix);
+
+        // Follow the derive on its own line, or on the same line as
+        // the type when they share one
+        let rest_of_line = content[offset..].split('\n').next().unwrap_or_default();
+        if rest_of_line.trim().is_empty() {
+            let line_start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+            format!("\n{}{}", indentation_of(&content[line_start..]), attribute)
+        } else {
+            format!(" {}", attribute)
+        }
+    }
+}
+
+/// Deprecated type aliases of renamed context selectors under their
+/// old names. A `#[deprecated]` re-export would not warn, as the
+/// compiler ignores the attribute on `use` items.
+struct SelectorAliases<'a>(Vec<SelectorAlias<'a>>);
+
+struct SelectorAlias<'a> {
+    old: &'a str,
+    new: String,
+    /// SNAFU 0.7 gives the selector a type parameter for each field.
+    fields: usize,
+}
+
+impl Code for SelectorAliases<'_> {
+    fn offset(&self, error_type: &ErrorType) -> usize {
+        error_type.end_offset
+    }
+
+    fn code(&self, content: &str, offset: usize) -> String {
+        let before = &content[..offset];
+        let previous_line = before.trim_end_matches('\n').rsplit('\n').next();
+        let indentation = indentation_of(previous_line.unwrap_or_default());
+
+        let mut code = String::new();
+        if !before.is_empty() && !before.ends_with('\n') {
+            code.push('\n');
+        }
+        for SelectorAlias { old, new, fields } in &self.0 {
+            let deprecated = format!(
+                "{}#[deprecated(note = \"renamed to `{}`\")]\n",
+                indentation, new
+            );
+
+            code.push('\n');
+            code.push_str(&deprecated);
+            if *fields == 0 {
+                code.push_str(&format!("{}pub type {} = {};\n", indentation, old, new));
+
+                // A unit struct is also used as a value
+                code.push_str(&deprecated);
+                code.push_str(&format!(
+                    "{0}#[allow(non_upper_case_globals)]\n{0}pub const {1}: {2} = {2};\n",
+                    indentation, old, new,
+                ));
+            } else {
+                let parameters: Vec<_> = (0..*fields).map(|i| format!("__T{}", i)).collect();
+                code.push_str(&format!(
+                    "{0}pub type {1}<{3}> = {2}<{3}>;\n",
+                    indentation,
+                    old,
+                    new,
+                    parameters.join(", "),
+                ));
+            }
+        }
+        code
+    }
+}
+
+fn indentation_of(line: &str) -> &str {
+    let code = line.trim_start_matches([' ', '\t']);
+    &line[..line.len() - code.len()]
+}
+
+/// Decides the context selector suffix of every error type in
+/// `reported_types` and of those given their own suffix in `opts`,
+/// and plans the code that must be added next to them. Attributes are
+/// only added to reported types, as SNAFU 0.6 rejects them.
+pub(crate) fn plan<'a>(
+    opts: &'a Options,
+    workspace_root: &Path,
+    scan: &'a Scan,
+    reported_types: &[&'a ErrorType],
+) -> Result<Insertions<'a>> {
+    let mut suffix_overrides: Vec<(&ErrorType, &str)> = Vec::new();
+    for (path, suffix) in &opts.type_suffixes {
+        if !suffix.is_empty() && syn::parse_str::<syn::Ident>(suffix).is_err() {
+            return Err(format!(
+                "The suffix `{}` for `{}` is not a valid identifier",
+                suffix, path,
+            )
+            .into());
+        }
+
+        // Other crates of the workspace may have a type at the same path
+        let error_types: Vec<_> = scan
+            .error_type(path)
+            .into_iter()
+            .filter(|t| workspace_root.join(&t.file).starts_with(&opts.directory))
+            .collect();
+        if error_types.is_empty() {
+            return Err(format!(
+                "Could not find the SNAFU error type `{}` to give the suffix `{}`",
+                path, suffix,
+            )
+            .into());
+        }
+
+        for error_type in error_types {
+            if !error_type.is_enum {
+                return Err(format!(
+                    "Only enums may have their own context selector suffix, but `{}` is a struct",
+                    path,
+                )
+                .into());
+            }
+
+            suffix_overrides.push((error_type, suffix));
+        }
+    }
+
+    if opts.preserve_names {
+        // Keep the SNAFU 0.6 names instead of renaming them
+        for &error_type in reported_types {
+            if !suffix_overrides.iter().any(|&(t, _)| t == error_type) {
+                suffix_overrides.push((error_type, ""));
+            }
+        }
+    }
+
+    let mut insertions = Insertions::default();
+    for &(error_type, suffix) in &suffix_overrides {
+        insertions
+            .type_suffixes
+            .insert(error_type.path.clone(), suffix.to_owned());
+
+        if !error_type.has_suffix_attribute && reported_types.contains(&error_type) {
+            let code = SuffixAttribute(suffix);
+            insertions.insert(workspace_root, error_type, Kind::SUFFIX_ATTRIBUTE, &code)?;
+        }
+    }
+
+    let renamed: Vec<_> = reported_types
+        .iter()
+        .filter_map(|&error_type| {
+            let overridden = suffix_overrides.iter().find(|&&(t, _)| t == error_type);
+            let suffix = match overridden {
+                Some(&(_, suffix)) => suffix,
+                // The suffix chosen by the author is unknown
+                None if error_type.has_suffix_attribute => return None,
+                None => &opts.suffix,
+            };
+
+            (!suffix.is_empty()).then_some(Renamed { error_type, suffix })
+        })
+        .collect();
+
+    if opts.deprecated_aliases {
+        for &Renamed { error_type, suffix } in &renamed {
+            let module = error_type.path.rsplit_once("::").map(|(m, _)| m);
+            let aliases: Vec<_> = error_type
+                .public_selectors
+                .iter()
+                .filter(|old| {
+                    let path =
+                        module.map_or_else(|| old.to_string(), |m| format!("{}::{}", m, old));
+                    !scan.renamed_imports.contains(&path)
+                })
+                .map(|old| SelectorAlias {
+                    old,
+                    new: error_type.upgraded_selector(old, suffix),
+                    fields: error_type.selector_fields.get(old).copied().unwrap_or(0),
+                })
+                .collect();
+
+            if !aliases.is_empty() {
+                let code = SelectorAliases(aliases);
+                insertions.insert(workspace_root, error_type, Kind::SELECTOR_ALIAS, &code)?;
+            }
+        }
+    }
+
+    insertions.renamed = renamed;
+    Ok(insertions)
+}
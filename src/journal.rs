@@ -0,0 +1,162 @@
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Records the original content of every file modified during a
+/// session so that the changes can be undone.
+///
+/// The journal is stored as a directory containing a copy of each
+/// original file and a manifest. The manifest is saved after every
+/// change so that an interrupted session can still be undone. The
+/// directory also contains a `.gitignore` so that git ignores the
+/// journal.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    dir: PathBuf,
+    manifest: Manifest,
+    started: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    files: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    path: PathBuf,
+    backup: String,
+    original_hash: String,
+    modified_hash: String,
+}
+
+impl Journal {
+    const MANIFEST: &'static str = "manifest.json";
+
+    /// Prepares a new session. Any previous session is only discarded
+    /// once the first file is changed.
+    pub(crate) fn begin(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            manifest: Manifest::default(),
+            started: false,
+        }
+    }
+
+    /// Replaces the content of the file at `path`, keeping a copy of
+    /// `original` the first time the file is changed in this session.
+    pub(crate) fn write(&mut self, path: &Path, original: &str, modified: &str) -> Result<()> {
+        if !self.started {
+            if self.dir.exists() {
+                fs::remove_dir_all(&self.dir)?;
+            }
+            fs::create_dir_all(&self.dir)?;
+            fs::write(self.dir.join(".gitignore"), "*\n")?;
+            self.started = true;
+        }
+
+        let index = match self.manifest.files.iter().position(|e| e.path == path) {
+            Some(index) => index,
+            None => {
+                let backup = self.manifest.files.len().to_string();
+                fs::write(self.dir.join(&backup), original)?;
+
+                self.manifest.files.push(Entry {
+                    path: path.to_owned(),
+                    backup,
+                    original_hash: hash(original),
+                    modified_hash: hash(original),
+                });
+                self.save()?;
+
+                self.manifest.files.len() - 1
+            }
+        };
+
+        fs::write(path, modified)?;
+
+        self.manifest.files[index].modified_hash = hash(modified);
+        self.save()
+    }
+
+    /// Restores every file changed so far in this session, returning
+    /// the paths of the restored files.
+    pub(crate) fn rollback(&mut self) -> Result<Vec<PathBuf>> {
+        if !self.started {
+            return Ok(Vec::new());
+        }
+
+        let restored = undo(&self.dir)?;
+        self.manifest = Manifest::default();
+        self.started = false;
+
+        Ok(restored)
+    }
+
+    fn save(&self) -> Result<()> {
+        let manifest = serde_json::to_string_pretty(&self.manifest)?;
+        fs::write(self.dir.join(Self::MANIFEST), manifest)?;
+        Ok(())
+    }
+}
+
+/// Restores every file recorded in the journal stored in `dir`,
+/// returning the paths of the restored files.
+///
+/// Nothing is restored if any of the files have been changed since
+/// the session ended.
+pub(crate) fn undo(dir: &Path) -> Result<Vec<PathBuf>> {
+    let manifest = match fs::read_to_string(dir.join(Journal::MANIFEST)) {
+        Ok(manifest) => manifest,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err("There is no session to undo".into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let manifest: Manifest = serde_json::from_str(&manifest)?;
+
+    let mut originals = Vec::new();
+    let mut changed = Vec::new();
+    for entry in &manifest.files {
+        let original = fs::read_to_string(dir.join(&entry.backup))?;
+        if hash(&original) != entry.original_hash {
+            return Err(format!("The backup of {} is corrupt", entry.path.display()).into());
+        }
+        originals.push(original);
+
+        let current = fs::read_to_string(&entry.path)?;
+        if hash(&current) != entry.modified_hash {
+            changed.push(entry.path.display().to_string());
+        }
+    }
+
+    if !changed.is_empty() {
+        return Err(format!(
+            "Refusing to undo because these files have changed since the session ended: {}",
+            changed.join(", "),
+        )
+        .into());
+    }
+
+    for (entry, original) in manifest.files.iter().zip(originals) {
+        fs::write(&entry.path, original)?;
+    }
+
+    fs::remove_dir_all(dir)?;
+
+    Ok(manifest.files.into_iter().map(|e| e.path).collect())
+}
+
+/// A 64-bit FNV-1a hash. This only needs to detect accidental
+/// changes, and unlike the standard library's hasher, it is stable
+/// between releases.
+fn hash(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+
+    format!("{:016x}", hash)
+}
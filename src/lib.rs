@@ -0,0 +1,1388 @@
+//! Helps upgrade SNAFU between semver-incompatible versions.
+//!
+//! The [`Migrator`] repeatedly builds a Cargo project, inspects the
+//! compiler's error messages, and rewrites the source code to account
+//! for the changes between SNAFU versions.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+};
+
+mod cargo;
+mod config;
+mod diagnostic;
+mod edit;
+mod fixer;
+mod git;
+mod insertion;
+mod journal;
+mod manifest;
+mod report;
+mod review;
+mod rule;
+mod sandbox;
+mod scan;
+mod semver;
+
+use cargo::Line;
+use git::Repository;
+use insertion::Insertions;
+use journal::Journal;
+use sandbox::Sandbox;
+
+pub use cargo::{workspace_root, Applicability, Code, Message, Span, Text};
+pub use config::Config;
+pub use diagnostic::{Diagnostic, Location};
+pub use edit::Edit;
+pub use fixer::{
+    Context, ContextSelectorRename, EqualSyntax, Fix, Fixer, Kind, WithContextArgument,
+};
+pub use report::Summary;
+pub use review::{AcceptAll, Interactive, Review};
+pub use rule::{Rule, SpanChoice};
+pub use scan::{ErrorType, Function, Scan};
+pub use semver::{ApiChange, SemverImpact};
+
+pub type Error = Box<dyn std::error::Error>;
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A value, such as a byte range or an edit, along with the kind of
+/// fix it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Category<T> {
+    pub kind: Kind,
+    pub value: T,
+}
+
+impl<T> Category<T> {
+    pub fn new(kind: Kind, value: T) -> Self {
+        Self { kind, value }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Category<U> {
+        Category::new(self.kind, f(self.value))
+    }
+
+    /// A short, human-readable description of the kind of fix.
+    pub fn name(&self) -> &str {
+        &self.kind.name
+    }
+}
+
+impl<T> PartialOrd for Category<T>
+where
+    T: PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<T> Ord for Category<T>
+where
+    T: Ord,
+{
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+/// The byte ranges to fix in each file, keyed by the file name
+/// relative to the workspace root.
+pub type FileMapping = BTreeMap<String, Vec<Category<(usize, usize)>>>;
+
+/// Configures a [`Migrator`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Options {
+    /// Do not write changes to disk.
+    pub dry_run: bool,
+
+    /// Extra arguments to `cargo check`.
+    pub extra_check_arg: Vec<String>,
+
+    /// What context selector suffix to use.
+    pub suffix: String,
+
+    /// What context selector suffix to use for specific error enums,
+    /// keyed by the path of the type within its crate, such as
+    /// `db::Error`. An empty suffix keeps the variant names, except
+    /// for a trailing `Error`. These types are given a
+    /// `#[snafu(context(suffix(...)))]` attribute.
+    pub type_suffixes: BTreeMap<String, String>,
+
+    /// Instead of renaming the context selectors, keep their SNAFU 0.6
+    /// names by giving each error enum that generates them a
+    /// `#[snafu(context(suffix(false)))]` attribute. Enums in
+    /// [`type_suffixes`](Self::type_suffixes) keep their suffix.
+    pub preserve_names: bool,
+
+    /// Add a deprecated type alias of each renamed `pub` context
+    /// selector under its SNAFU 0.6 name next to the error enum, so
+    /// that users of a library keep compiling.
+    pub deprecated_aliases: bool,
+
+    /// What directory to make changes in. Files outside of this
+    /// directory will never be modified.
+    pub directory: PathBuf,
+
+    /// How many iterations to perform before giving up.
+    pub max_iterations: usize,
+
+    /// Also apply the compiler's machine-applicable suggestions, such
+    /// as removing unused imports.
+    pub apply_compiler_suggestions: bool,
+
+    /// Restore every changed file if the migration fails. May not be
+    /// combined with [`commit_each_iteration`](Self::commit_each_iteration).
+    pub rollback_on_failure: bool,
+
+    /// Allow making changes when the git repository containing
+    /// `directory` has uncommitted changes.
+    pub allow_dirty: bool,
+
+    /// Create a git commit after each iteration.
+    pub commit_each_iteration: bool,
+
+    /// Create and switch to a new git branch with this name before
+    /// making any changes.
+    pub branch: Option<String>,
+
+    /// Before the first iteration, check that the workspace builds,
+    /// then change every requirement on SNAFU 0.6 in the workspace's
+    /// manifests to [`Options::UPGRADED_REQUIREMENT`] and update SNAFU
+    /// in the lockfile.
+    pub upgrade_dependency: bool,
+
+    /// Show detailed information.
+    pub verbose: bool,
+}
+
+impl Options {
+    pub const DEFAULT_SUFFIX: &'static str = "Snafu";
+    pub const DEFAULT_MAXIMUM_ITERATIONS: usize = 5;
+    pub const UPGRADED_REQUIREMENT: &'static str = "0.7";
+
+    /// Creates options that make changes inside of `directory`,
+    /// using the default values for everything else.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            dry_run: false,
+            extra_check_arg: Vec::new(),
+            suffix: Self::DEFAULT_SUFFIX.to_string(),
+            type_suffixes: BTreeMap::new(),
+            preserve_names: false,
+            deprecated_aliases: false,
+            directory: directory.into(),
+            max_iterations: Self::DEFAULT_MAXIMUM_ITERATIONS,
+            apply_compiler_suggestions: false,
+            rollback_on_failure: false,
+            allow_dirty: false,
+            commit_each_iteration: false,
+            branch: None,
+            upgrade_dependency: false,
+            verbose: false,
+        }
+    }
+}
+
+/// The result of a single build-and-fix cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Iteration {
+    /// The fixes planned from the compiler's error messages, except
+    /// for those declined by the review.
+    pub fixes: FileMapping,
+
+    /// The compiler messages that led to the planned fixes.
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// The compiler messages that did not lead to any planned fix.
+    pub unhandled: Vec<Diagnostic>,
+
+    /// The edits applied to each file, after review.
+    pub edits: BTreeMap<PathBuf, Vec<Category<Edit>>>,
+
+    /// Edits that were not applied because they conflict with another
+    /// edit.
+    pub conflicts: BTreeMap<PathBuf, Vec<Conflict>>,
+
+    /// Edits that were not applied because the review declined them.
+    /// The code they would have fixed is not part of
+    /// [`fixes`](Self::fixes), and its compiler messages are
+    /// unhandled.
+    pub declined: Vec<Category<Location>>,
+
+    /// Fixes that were not planned because the file no longer
+    /// contains the source code the compiler reported. These are
+    /// planned again in the next iteration.
+    pub stale: Vec<Location>,
+
+    /// Names the compiler could not find that are not context
+    /// selectors of any SNAFU error type in the workspace. These
+    /// were left unchanged.
+    pub unrelated_names: Vec<UnrelatedName>,
+
+    /// Each file that was modified (or would have been, for a dry
+    /// run).
+    pub modified_files: BTreeMap<PathBuf, FileChange>,
+
+    /// The changes to the public API of the library crates.
+    pub api_changes: Vec<ApiChange>,
+}
+
+impl Iteration {
+    pub fn is_empty(&self) -> bool {
+        self.fixes.is_empty()
+    }
+
+    /// How many edits of each kind were applied, keyed by
+    /// [`Category::name`].
+    pub fn edit_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for edit in self.edits.values().flatten() {
+            *counts.entry(edit.name()).or_default() += 1;
+        }
+        counts
+    }
+}
+
+/// An edit that was not applied because it conflicts with another
+/// edit.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Conflict {
+    pub edit: Category<Edit>,
+    /// The applied edit that `edit` conflicts with.
+    pub conflicts_with: Category<Edit>,
+    /// The 1-based line of the start of the edit.
+    pub line: usize,
+    /// The 1-based column of the start of the edit, in characters.
+    pub column: usize,
+}
+
+/// Code reported by the compiler that a fixer decided not to change,
+/// such as a name that is not a SNAFU context selector.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnrelatedName {
+    /// The file containing the name, relative to the workspace root.
+    pub file: PathBuf,
+    pub start: usize,
+    pub end: usize,
+    /// The 1-based line of `start`.
+    pub line: usize,
+    /// The 1-based column of `start`, in characters.
+    pub column: usize,
+    pub name: String,
+}
+
+/// The content of a file before and after applying fixes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub original: String,
+    pub modified: String,
+}
+
+impl FileChange {
+    /// The number of unchanged lines shown around each change.
+    pub const DIFF_CONTEXT_LINES: usize = 3;
+
+    /// Formats the change as a unified diff, using `path` in the
+    /// file headers.
+    pub fn unified_diff(&self, path: &Path) -> String {
+        let path = path.display();
+
+        similar::TextDiff::from_lines(&self.original, &self.modified)
+            .unified_diff()
+            .context_radius(Self::DIFF_CONTEXT_LINES)
+            .header(&format!("a/{}", path), &format!("b/{}", path))
+            .to_string()
+    }
+}
+
+/// How a complete migration ended.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// No more fixes could be identified.
+    Converged,
+
+    /// Fixes were still being identified after the maximum number of
+    /// iterations.
+    IterationLimit,
+
+    /// An iteration identified exactly the same fixes as the previous
+    /// one.
+    NoProgress,
+}
+
+/// A step of a migration, reported to the function given to
+/// [`Migrator::on_progress`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Progress<'a> {
+    /// The first check build is starting, which may take a while.
+    InitialBuild,
+
+    /// Another check build is starting to find the errors left after
+    /// the previous iteration.
+    FollowUpBuild,
+
+    /// The SNAFU requirement in the manifest was upgraded.
+    UpgradedDependency { manifest: &'a Path },
+
+    /// The SNAFU requirement in the manifest was left alone because
+    /// the manifest is not within the directory being fixed.
+    SkippedDependency {
+        manifest: &'a Path,
+        directory: &'a Path,
+    },
+}
+
+/// Every iteration performed by [`Migrator::run`] and how it ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// The root of the Cargo workspace that was migrated.
+    pub workspace_root: PathBuf,
+
+    pub iterations: Vec<Iteration>,
+    pub outcome: Outcome,
+
+    /// The files that were restored to their original content
+    /// because the migration failed.
+    pub rolled_back: Vec<PathBuf>,
+
+    /// The manifests and lockfile changed to upgrade the SNAFU
+    /// dependency before the first iteration.
+    pub dependency_changes: BTreeMap<PathBuf, FileChange>,
+}
+
+impl Migration {
+    /// Combines every iteration into the overall change made to each
+    /// file.
+    pub fn changes(&self) -> BTreeMap<PathBuf, FileChange> {
+        let mut changes = self.dependency_changes.clone();
+
+        for iteration in &self.iterations {
+            for (path, change) in &iteration.modified_files {
+                changes
+                    .entry(path.clone())
+                    .and_modify(|c| c.modified.clone_from(&change.modified))
+                    .or_insert_with(|| change.clone());
+            }
+        }
+
+        changes.retain(|_, c| c.original != c.modified);
+        changes
+    }
+}
+
+/// Applies the SNAFU upgrade fixes to a Cargo project.
+#[derive(Debug, Clone)]
+pub struct Migrator {
+    opts: Options,
+    fixers: Vec<Arc<dyn Fixer>>,
+    progress: ProgressFn,
+    current_dir: Option<PathBuf>,
+    target_dir: Option<PathBuf>,
+    journal: bool,
+}
+
+/// The function given to [`Migrator::on_progress`].
+#[derive(Clone)]
+struct ProgressFn(Arc<dyn Fn(Progress<'_>) + Send + Sync>);
+
+impl ProgressFn {
+    fn report(&self, step: Progress<'_>) {
+        (self.0)(step)
+    }
+}
+
+impl fmt::Debug for ProgressFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressFn")
+    }
+}
+
+impl Migrator {
+    /// The directory in the workspace root where the original content
+    /// of changed files is kept until the next run.
+    pub const JOURNAL_DIR: &'static str = ".snafu-upgrade";
+
+    /// Creates a migrator with the built-in fixers for upgrading from
+    /// SNAFU 0.6 to 0.7.
+    pub fn new(opts: Options) -> Self {
+        Self {
+            opts,
+            fixers: fixer::builtin(),
+            progress: ProgressFn(Arc::new(|_| {})),
+            current_dir: None,
+            target_dir: None,
+            journal: true,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opts
+    }
+
+    /// Also uses `fixer` to find and fix problems. When fixers
+    /// change overlapping code, the fix that starts first is used.
+    pub fn add_fixer(&mut self, fixer: impl Fixer + 'static) {
+        self.fixers.push(Arc::new(fixer));
+    }
+
+    /// Calls `progress` as each step of a migration starts or
+    /// finishes. Nothing is reported by default.
+    pub fn on_progress(&mut self, progress: impl Fn(Progress<'_>) + Send + Sync + 'static) {
+        self.progress = ProgressFn(Arc::new(progress));
+    }
+
+    /// Builds and fixes the project until no more fixes are found,
+    /// progress stops, or the iteration limit is reached.
+    ///
+    /// For a dry run, every iteration is performed on a temporary
+    /// copy of the workspace. The reported paths refer to the
+    /// original workspace.
+    pub fn run(&self) -> Result<Migration> {
+        self.run_with_review(&mut AcceptAll)
+    }
+
+    /// Like [`Migrator::run`], but every planned edit must be
+    /// approved by `review` before it is applied.
+    pub fn run_with_review(&self, review: &mut dyn Review) -> Result<Migration> {
+        // Restoring the files would leave the commits behind
+        if self.opts.rollback_on_failure && self.opts.commit_each_iteration {
+            return Err(
+                "Rolling back on failure cannot be combined with committing each iteration; \
+                 reset the branch to undo the commits instead"
+                    .into(),
+            );
+        }
+
+        if self.opts.dry_run {
+            self.run_in_sandbox(review)
+        } else {
+            let repository = self.prepare_repository()?;
+            self.run_in_place(review, repository.as_ref())
+        }
+    }
+
+    /// Checks that the git repository (if any) is safe to change and
+    /// switches to the requested branch.
+    fn prepare_repository(&self) -> Result<Option<Repository>> {
+        let opts = &self.opts;
+
+        let repository = match Repository::discover(&opts.directory)? {
+            Some(repository) => repository,
+            None if opts.commit_each_iteration || opts.branch.is_some() => {
+                return Err(format!(
+                    "Creating git commits or branches requires {} to be inside of a git repository",
+                    opts.directory.display(),
+                )
+                .into());
+            }
+            None => return Ok(None),
+        };
+
+        if opts.verbose {
+            dbg!(&repository);
+        }
+
+        if !opts.allow_dirty && repository.is_dirty()? {
+            return Err(format!(
+                "The git repository at {} has uncommitted changes. Commit or stash them before continuing, or allow a dirty working tree",
+                repository.root().display(),
+            )
+            .into());
+        }
+
+        if let Some(branch) = &opts.branch {
+            repository.create_branch(branch)?;
+        }
+
+        Ok(Some(repository))
+    }
+
+    fn run_in_sandbox(&self, review: &mut dyn Review) -> Result<Migration> {
+        let metadata = cargo::metadata(self.cargo())?;
+        let manifests = metadata.manifests();
+        let workspace_root = PathBuf::from(metadata.workspace_root);
+        // Sharing the target directory avoids rebuilding every dependency
+        let target_dir = PathBuf::from(metadata.target_directory);
+
+        let journal_dir = workspace_root.join(Self::JOURNAL_DIR);
+        let sandbox = Arc::new(Sandbox::create(
+            &workspace_root,
+            &[&target_dir, &journal_dir],
+        )?);
+        let linked_manifests = sandbox.link_external_dependencies(&manifests)?;
+
+        if self.opts.verbose {
+            dbg!(&sandbox);
+        }
+
+        let mut opts = self.opts.clone();
+        opts.dry_run = false;
+        opts.directory = sandbox.to_sandbox(&opts.directory)?;
+
+        // Report the paths of the original workspace
+        let progress = {
+            let progress = self.progress.clone();
+            let sandbox = sandbox.clone();
+            ProgressFn(Arc::new(move |step: Progress<'_>| match step {
+                Progress::UpgradedDependency { manifest } => {
+                    let manifest = sandbox.to_original(manifest);
+                    progress.report(Progress::UpgradedDependency {
+                        manifest: &manifest,
+                    })
+                }
+                Progress::SkippedDependency {
+                    manifest,
+                    directory,
+                } => {
+                    let manifest = sandbox.to_original(manifest);
+                    let directory = sandbox.to_original(directory);
+                    progress.report(Progress::SkippedDependency {
+                        manifest: &manifest,
+                        directory: &directory,
+                    })
+                }
+                step => progress.report(step),
+            }))
+        };
+
+        let migrator = Self {
+            opts,
+            fixers: self.fixers.clone(),
+            progress,
+            current_dir: Some(sandbox.root.clone()),
+            target_dir: Some(target_dir),
+            // Nothing outside of the sandbox is changed, so there's nothing to undo
+            journal: false,
+        };
+
+        let mut migration = migrator.run_in_place(review, None)?;
+        migration.workspace_root = workspace_root;
+
+        let mut dependency_changes = BTreeMap::new();
+        for (path, mut change) in std::mem::take(&mut migration.dependency_changes) {
+            let path = sandbox.to_original(&path);
+
+            // Show the change to the manifest that will be upgraded,
+            // without the linked path dependencies
+            if linked_manifests.contains(&path) {
+                change.original = fs::read_to_string(&path)?;
+                change.modified =
+                    manifest::upgrade_snafu(&change.original, Options::UPGRADED_REQUIREMENT)?
+                        .unwrap_or_else(|| change.original.clone());
+            }
+
+            dependency_changes.insert(path, change);
+        }
+        migration.dependency_changes = dependency_changes;
+
+        for iteration in &mut migration.iterations {
+            iteration.modified_files = std::mem::take(&mut iteration.modified_files)
+                .into_iter()
+                .map(|(path, change)| (sandbox.to_original(&path), change))
+                .collect();
+            iteration.edits = std::mem::take(&mut iteration.edits)
+                .into_iter()
+                .map(|(path, edits)| (sandbox.to_original(&path), edits))
+                .collect();
+            iteration.conflicts = std::mem::take(&mut iteration.conflicts)
+                .into_iter()
+                .map(|(path, conflicts)| (sandbox.to_original(&path), conflicts))
+                .collect();
+        }
+
+        Ok(migration)
+    }
+
+    fn run_in_place(
+        &self,
+        review: &mut dyn Review,
+        repository: Option<&Repository>,
+    ) -> Result<Migration> {
+        let opts = &self.opts;
+
+        self.progress.report(Progress::InitialBuild);
+
+        let mut journal = self.begin_journal()?;
+
+        let migration = self.converge(review, journal.as_mut(), repository);
+
+        let journal = match journal.as_mut() {
+            Some(journal) if opts.rollback_on_failure => journal,
+            _ => return migration,
+        };
+
+        match migration {
+            Ok(mut migration) => {
+                if migration.outcome != Outcome::Converged {
+                    migration.rolled_back = journal.rollback()?;
+                }
+                Ok(migration)
+            }
+            Err(e) => {
+                let restored = journal.rollback()?;
+                Err(format!("{} (restored {} changed files)", e, restored.len()).into())
+            }
+        }
+    }
+
+    fn converge(
+        &self,
+        review: &mut dyn Review,
+        mut journal: Option<&mut Journal>,
+        repository: Option<&Repository>,
+    ) -> Result<Migration> {
+        let opts = &self.opts;
+
+        let dependency_changes = if opts.upgrade_dependency {
+            self.upgrade_dependency(journal.as_deref_mut(), repository)?
+        } else {
+            BTreeMap::new()
+        };
+
+        let mut depth = 0;
+        let first_fix = self.apply_once_inner(review, journal.as_deref_mut())?;
+        self.commit_iteration(repository, 1, &first_fix)?;
+        let mut iterations = vec![first_fix];
+
+        let outcome = loop {
+            if opts.verbose {
+                dbg!(depth);
+            }
+
+            let last_fix = &iterations[iterations.len() - 1];
+
+            if last_fix.is_empty() {
+                break Outcome::Converged;
+            }
+
+            if depth > opts.max_iterations {
+                break Outcome::IterationLimit;
+            }
+
+            self.progress.report(Progress::FollowUpBuild);
+            let current_fix = self.apply_once_inner(review, journal.as_deref_mut())?;
+            self.commit_iteration(repository, iterations.len() + 1, &current_fix)?;
+
+            if last_fix.fixes == current_fix.fixes {
+                iterations.push(current_fix);
+                break Outcome::NoProgress;
+            }
+
+            iterations.push(current_fix);
+            depth += 1;
+        };
+
+        let workspace_root = cargo::metadata(self.cargo())?.workspace_root.into();
+
+        Ok(Migration {
+            workspace_root,
+            iterations,
+            outcome,
+            rolled_back: Vec::new(),
+            dependency_changes,
+        })
+    }
+
+    /// Checks that the workspace builds, then upgrades every
+    /// requirement on SNAFU 0.6 and the lockfile. Returns the changed
+    /// files.
+    fn upgrade_dependency(
+        &self,
+        mut journal: Option<&mut Journal>,
+        repository: Option<&Repository>,
+    ) -> Result<BTreeMap<PathBuf, FileChange>> {
+        let opts = &self.opts;
+
+        let metadata = cargo::metadata(self.cargo())?;
+        let manifests = metadata.manifests();
+        let workspace_root = PathBuf::from(metadata.workspace_root);
+
+        let mut changes = BTreeMap::new();
+
+        for path in manifests {
+            let original = fs::read_to_string(&path)?;
+            let modified = manifest::upgrade_snafu(&original, Options::UPGRADED_REQUIREMENT)
+                .map_err(|e| format!("Could not upgrade SNAFU in {}: {}", path.display(), e))?;
+            let modified = match modified {
+                Some(modified) => modified,
+                None => continue,
+            };
+
+            if !path.starts_with(&opts.directory) {
+                self.progress.report(Progress::SkippedDependency {
+                    manifest: &path,
+                    directory: &opts.directory,
+                });
+                continue;
+            }
+
+            changes.insert(path, FileChange { original, modified });
+        }
+
+        // Already upgraded
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+
+        let mut check_command = self.cargo();
+        check_command.arg("check").args(&opts.extra_check_arg);
+        if !check_command.status()?.success() {
+            return Err("The workspace must build before upgrading SNAFU".into());
+        }
+
+        for (path, change) in &changes {
+            match journal.as_deref_mut() {
+                Some(journal) => journal.write(path, &change.original, &change.modified)?,
+                None => fs::write(path, &change.modified)?,
+            }
+            self.progress
+                .report(Progress::UpgradedDependency { manifest: path });
+        }
+
+        // Cargo creates the lockfile during the first build if there isn't one
+        let lockfile = workspace_root.join("Cargo.lock");
+        if lockfile.exists() {
+            let original = fs::read_to_string(&lockfile)?;
+            for version in manifest::locked_snafu_0_6(&original)? {
+                let status = self
+                    .cargo()
+                    .args(["update", "--package"])
+                    .arg(format!("snafu@{}", version))
+                    .status()?;
+
+                if !status.success() {
+                    return Err(
+                        format!("Could not update SNAFU {} in the lockfile", version).into(),
+                    );
+                }
+            }
+
+            let modified = fs::read_to_string(&lockfile)?;
+            if modified != original {
+                if let Some(journal) = journal {
+                    journal.write(&lockfile, &original, &modified)?;
+                }
+                changes.insert(lockfile, FileChange { original, modified });
+            }
+        }
+
+        if let Some(repository) = repository.filter(|_| opts.commit_each_iteration) {
+            let paths: Vec<_> = changes.keys().map(PathBuf::as_path).collect();
+            let message = format!("Upgrade SNAFU to {}", Options::UPGRADED_REQUIREMENT);
+            repository.commit(&paths, &message)?;
+        }
+
+        Ok(changes)
+    }
+
+    fn commit_iteration(
+        &self,
+        repository: Option<&Repository>,
+        number: usize,
+        iteration: &Iteration,
+    ) -> Result<()> {
+        let repository = match repository {
+            Some(repository) if self.opts.commit_each_iteration => repository,
+            _ => return Ok(()),
+        };
+
+        if iteration.modified_files.is_empty() {
+            return Ok(());
+        }
+
+        let mut message = format!("Upgrade SNAFU (iteration {})\n\n", number);
+        for (name, count) in iteration.edit_counts() {
+            message.push_str(&format!("- {}: {}\n", name, count));
+        }
+
+        let paths: Vec<_> = iteration
+            .modified_files
+            .keys()
+            .map(PathBuf::as_path)
+            .collect();
+        repository.commit(&paths, &message)
+    }
+
+    /// Performs one build, then plans and applies the fixes for the
+    /// reported errors.
+    pub fn apply_once(&self) -> Result<Iteration> {
+        self.apply_once_with_review(&mut AcceptAll)
+    }
+
+    /// Like [`Migrator::apply_once`], but every planned edit must be
+    /// approved by `review` before it is applied.
+    pub fn apply_once_with_review(&self, review: &mut dyn Review) -> Result<Iteration> {
+        let mut journal = self.begin_journal()?;
+        self.apply_once_inner(review, journal.as_mut())
+    }
+
+    /// Restores every file changed by the most recent run (or call to
+    /// [`Migrator::apply_once`]), returning the paths of the restored
+    /// files.
+    ///
+    /// Nothing is restored if any of those files have changed since.
+    pub fn undo(&self) -> Result<Vec<PathBuf>> {
+        journal::undo(&self.journal_dir()?)
+    }
+
+    /// The journal is kept outside of the target directory so that
+    /// `cargo clean` does not remove it.
+    fn journal_dir(&self) -> Result<PathBuf> {
+        let metadata = cargo::metadata(self.cargo())?;
+        let workspace_root = PathBuf::from(metadata.workspace_root);
+
+        Ok(workspace_root.join(Self::JOURNAL_DIR))
+    }
+
+    fn begin_journal(&self) -> Result<Option<Journal>> {
+        if self.journal && !self.opts.dry_run {
+            Ok(Some(Journal::begin(self.journal_dir()?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn apply_once_inner(
+        &self,
+        review: &mut dyn Review,
+        journal: Option<&mut Journal>,
+    ) -> Result<Iteration> {
+        let build = self.build()?;
+        let scan = scan::scan(&build.workspace_root, &[&build.target_dir])?;
+
+        if self.opts.verbose {
+            dbg!(&scan);
+        }
+
+        let plan = self.plan(&build, &scan)?;
+        let applied = self.apply(&build, &plan, review, journal)?;
+
+        Ok(self.report(&build, &plan, applied))
+    }
+
+    /// Checks the workspace, collecting the compiler's messages.
+    fn build(&self) -> Result<Build> {
+        let opts = &self.opts;
+
+        let mut build_command = self.cargo();
+        build_command.arg("check");
+        for arg in &opts.extra_check_arg {
+            build_command.arg(arg);
+        }
+        build_command.args(["--message-format", "json"]);
+
+        if opts.verbose {
+            dbg!(&build_command);
+        }
+
+        let output = build_command.output()?;
+        let stdout = String::from_utf8(output.stdout)?;
+
+        if opts.verbose {
+            dbg!(&stdout);
+        }
+
+        let lines: Vec<Line> = stdout
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+
+        if opts.verbose {
+            dbg!(&lines);
+        }
+
+        let messages = lines
+            .into_iter()
+            .flat_map(|l| match l {
+                Line::CompilerMessage { message } => Some(message),
+                Line::Other => None,
+            })
+            .collect();
+
+        let metadata = cargo::metadata(self.cargo())?;
+
+        Ok(Build {
+            messages,
+            workspace_root: metadata.workspace_root.into(),
+            target_dir: metadata.target_directory.into(),
+        })
+    }
+
+    /// Decides which fixers may change the code reported by the
+    /// build, and what code is added next to the reported error types.
+    fn plan<'a>(&'a self, build: &'a Build, scan: &'a Scan) -> Result<FixPlan<'a>> {
+        let opts = &self.opts;
+        let workspace_root = &build.workspace_root;
+        let messages = &build.messages;
+
+        // Warnings from outside of the safe directory are common, so
+        // ignore them instead of failing.
+        let compiler_suggestions: Vec<_> = messages
+            .iter()
+            .enumerate()
+            .filter(|_| opts.apply_compiler_suggestions)
+            .flat_map(|(i, m)| {
+                m.machine_applicable_suggestions()
+                    .map(move |(j, s)| ((i, j), s))
+            })
+            .filter(|(_, s)| {
+                workspace_root
+                    .join(&s.file_name)
+                    .starts_with(&opts.directory)
+            })
+            .collect();
+
+        if opts.verbose {
+            dbg!(&compiler_suggestions);
+        }
+
+        // More than one fixer may be interested in the same span, so
+        // remember the order they should be tried in
+        let mut claimed = BTreeSet::new();
+        let mut span_messages = BTreeMap::new();
+        for message in messages {
+            for (i, fixer) in self.fixers.iter().enumerate() {
+                for span in fixer.spans(message) {
+                    let key = (span.file_name.as_str(), span.range());
+                    span_messages.entry(key).or_insert((message, span));
+                    claimed.insert((span, i));
+                }
+            }
+        }
+
+        let mut files: BTreeMap<_, FilePlan<'_>> = BTreeMap::new();
+        // The enums that generate the code the fixers change
+        let mut reported_types: Vec<&ErrorType> = Vec::new();
+
+        for (span, i) in claimed {
+            let fixer = &*self.fixers[i];
+            files
+                .entry(span.file_name.clone())
+                .or_default()
+                .spans
+                .push((fixer, span));
+
+            for error_type in fixer.error_types(span, scan) {
+                let in_directory = workspace_root
+                    .join(&error_type.file)
+                    .starts_with(&opts.directory);
+
+                if error_type.is_enum && in_directory && !reported_types.contains(&error_type) {
+                    reported_types.push(error_type);
+                }
+            }
+        }
+
+        for &(_, span) in &compiler_suggestions {
+            files
+                .entry(span.file_name.clone())
+                .or_default()
+                .compiler_suggestions
+                .push(span);
+        }
+
+        let mut suggestions: BTreeMap<_, edit::Suggestions<'_>> = BTreeMap::new();
+        for (&(file_name, range), &(message, span)) in &span_messages {
+            for suggestion in message.suggestions_for(span) {
+                suggestions
+                    .entry(file_name)
+                    .or_default()
+                    .names
+                    .entry(range)
+                    .or_default()
+                    .push(suggestion);
+            }
+        }
+        for &(id, span) in &compiler_suggestions {
+            if let Some(replacement) = &span.suggested_replacement {
+                suggestions
+                    .entry(&span.file_name)
+                    .or_default()
+                    .machine_applicable
+                    .insert(span.range(), (id, replacement));
+            }
+        }
+
+        if opts.verbose {
+            dbg!(&suggestions);
+        }
+
+        let mut insertions = insertion::plan(opts, workspace_root, scan, &reported_types)?;
+        for (file_name, edits) in std::mem::take(&mut insertions.edits) {
+            files.entry(file_name).or_default().insertions = edits;
+        }
+
+        if opts.verbose {
+            dbg!(&files);
+        }
+
+        Ok(FixPlan {
+            files,
+            messages: span_messages
+                .into_iter()
+                .map(|(key, (message, _))| (key, message))
+                .collect(),
+            suggestions,
+            insertions,
+            scan,
+        })
+    }
+
+    /// Asks the fixers how to change each file, then applies the
+    /// reviewed edits.
+    fn apply(
+        &self,
+        build: &Build,
+        plan: &FixPlan<'_>,
+        review: &mut dyn Review,
+        mut journal: Option<&mut Journal>,
+    ) -> Result<Applied> {
+        let opts = &self.opts;
+        let mut applied = Applied::default();
+
+        for (relative_filename, file_plan) in &plan.files {
+            let filename = build.workspace_root.join(relative_filename);
+            if !filename.starts_with(&opts.directory) {
+                return Err(format!(
+                    "Attempted to update file outside of safe directory. {} is not within {}",
+                    filename.display(),
+                    opts.directory.display(),
+                )
+                .into());
+            }
+
+            if opts.verbose {
+                dbg!(&filename);
+            }
+
+            let original = fs::read_to_string(&filename)?;
+            let in_file = |e: Error| format!("{}:{}", relative_filename, e);
+
+            let reported_spans = file_plan
+                .spans
+                .iter()
+                .map(|&(_, span)| span)
+                .chain(file_plan.compiler_suggestions.iter().copied());
+
+            // The file may have changed since it was compiled
+            let mut stale_ranges = BTreeSet::new();
+            for span in reported_spans.clone() {
+                if !span.matches(&original) && stale_ranges.insert(span.range()) {
+                    applied.stale.push(Location::from(span));
+                }
+            }
+            let is_stale = |span: &Span| stale_ranges.contains(&span.range());
+
+            let insertion_ranges = file_plan
+                .insertions
+                .iter()
+                .map(|e| (e.value.start, e.value.end));
+            for range in reported_spans
+                .filter(|s| !is_stale(s))
+                .map(Span::range)
+                .chain(insertion_ranges)
+            {
+                edit::check_range(&original, range).map_err(in_file)?;
+            }
+
+            let no_suggestions = edit::Suggestions::default();
+            let file_suggestions = plan
+                .suggestions
+                .get(relative_filename.as_str())
+                .unwrap_or(&no_suggestions);
+
+            let mut fixes = Vec::new();
+            let mut fixer_edits = file_plan.insertions.clone();
+            // The range of the edit made for each fixed range
+            let mut edit_ranges = BTreeMap::new();
+
+            // Each range is fixed by the first fixer that considers it
+            // related
+            let mut decided = BTreeSet::new();
+            let mut unrelated = BTreeSet::new();
+
+            for &(fixer, span) in &file_plan.spans {
+                let range = span.range();
+
+                // Stale code is planned again by the next iteration
+                if is_stale(span) {
+                    fixes.push(Category::new(fixer.kind(), range));
+                    continue;
+                }
+
+                if decided.contains(&range) {
+                    continue;
+                }
+
+                let message = plan.messages[&(relative_filename.as_str(), range)];
+
+                let context = Context {
+                    message,
+                    file: Path::new(relative_filename),
+                    content: &original,
+                    suffix: &opts.suffix,
+                    type_suffixes: &plan.insertions.type_suffixes,
+                    scan: plan.scan,
+                    suggestions: file_suggestions
+                        .names
+                        .get(&range)
+                        .map_or(&[], Vec::as_slice),
+                };
+
+                match fixer.fix(&context, range).map_err(in_file)? {
+                    Fix::Edit(edit) => {
+                        decided.insert(range);
+                        edit_ranges.insert(range, (edit.start, edit.end));
+                        fixer_edits.push(Category::new(fixer.kind(), edit));
+                        fixes.push(Category::new(fixer.kind(), range));
+                    }
+                    Fix::Unchanged => {
+                        decided.insert(range);
+                        fixes.push(Category::new(fixer.kind(), range));
+                    }
+                    Fix::Unrelated => {
+                        unrelated.insert(range);
+                    }
+                }
+            }
+
+            for &(start, end) in unrelated.difference(&decided) {
+                let (line, column) = edit::line_column(&original, start);
+                applied.unrelated_names.push(UnrelatedName {
+                    file: relative_filename.into(),
+                    start,
+                    end,
+                    line,
+                    column,
+                    name: original[start..end].to_owned(),
+                });
+            }
+
+            let mut compiler_suggestion_ranges = Vec::new();
+            for &span in &file_plan.compiler_suggestions {
+                if !is_stale(span) {
+                    compiler_suggestion_ranges.push(span.range());
+                }
+                fixes.push(Category::new(Kind::COMPILER_SUGGESTION, span.range()));
+            }
+
+            fixes.extend(
+                file_plan
+                    .insertions
+                    .iter()
+                    .map(|e| Category::new(e.kind.clone(), (e.value.start, e.value.end))),
+            );
+
+            let file_plan = edit::plan(fixer_edits, &compiler_suggestion_ranges, file_suggestions);
+
+            if opts.verbose {
+                dbg!(&file_plan);
+            }
+
+            if !file_plan.conflicts.is_empty() {
+                let file_conflicts = file_plan
+                    .conflicts
+                    .into_iter()
+                    .map(|(edit, conflicts_with)| {
+                        let (line, column) = edit::line_column(&original, edit.value.start);
+                        Conflict {
+                            edit,
+                            conflicts_with,
+                            line,
+                            column,
+                        }
+                    })
+                    .collect();
+                applied.conflicts.insert(filename.clone(), file_conflicts);
+            }
+
+            let planned = file_plan.edits.clone();
+            let edits = review.review(Path::new(relative_filename), &original, file_plan.edits)?;
+
+            // Declined fixes are not planned, so that skipping them
+            // does not prevent the migration from converging
+            let is_accepted = |planned: &Category<Edit>| {
+                edits.iter().any(|e| {
+                    e.kind == planned.kind
+                        && (e.value.start, e.value.end) == (planned.value.start, planned.value.end)
+                })
+            };
+            let declined: Vec<_> = planned.into_iter().filter(|e| !is_accepted(e)).collect();
+            fixes.retain(|fix| {
+                let range = edit_ranges.get(&fix.value).unwrap_or(&fix.value);
+                !declined
+                    .iter()
+                    .any(|d| d.kind == fix.kind && (d.value.start, d.value.end) == *range)
+            });
+            for edit in declined {
+                let (line, column) = edit::line_column(&original, edit.value.start);
+                let (end_line, end_column) = edit::line_column(&original, edit.value.end);
+                applied.declined.push(edit.map(|e| Location {
+                    file: relative_filename.clone(),
+                    start: e.start,
+                    end: e.end,
+                    line,
+                    column,
+                    end_line,
+                    end_column,
+                }));
+            }
+
+            if !fixes.is_empty() {
+                applied.fixes.insert(relative_filename.clone(), fixes);
+            }
+
+            if edits.is_empty() {
+                continue;
+            }
+
+            let modified_content = edit::apply(&original, &edits).map_err(in_file)?;
+
+            if opts.verbose {
+                dbg!(&modified_content);
+            }
+
+            if !opts.dry_run {
+                match journal.as_deref_mut() {
+                    Some(journal) => journal.write(&filename, &original, &modified_content)?,
+                    None => fs::write(&filename, &modified_content)?,
+                }
+            }
+
+            let change = FileChange {
+                original,
+                modified: modified_content,
+            };
+            applied.modified_files.insert(filename.clone(), change);
+            applied.edits.insert(filename, edits);
+        }
+
+        Ok(applied)
+    }
+
+    /// Sorts the compiler messages by whether a fix was planned for
+    /// them and finds the changes to the public API.
+    fn report(&self, build: &Build, plan: &FixPlan<'_>, applied: Applied) -> Iteration {
+        let Applied {
+            fixes,
+            edits,
+            modified_files,
+            conflicts,
+            declined,
+            stale,
+            unrelated_names,
+        } = applied;
+
+        let mut file_edits = BTreeMap::new();
+        for (filename, edits) in &edits {
+            let relative = filename
+                .strip_prefix(&build.workspace_root)
+                .unwrap_or(filename);
+            let original = modified_files[filename].original.as_str();
+            file_edits.insert(relative, (original, edits.as_slice()));
+        }
+        let api_changes = semver::api_changes(plan.scan, &plan.insertions.renamed, &file_edits);
+
+        let is_planned = |m: &cargo::Message| {
+            m.all_spans().any(|s| {
+                fixes.get(&s.file_name).is_some_and(|ranges| {
+                    ranges.iter().any(|r| r.value == (s.byte_start, s.byte_end))
+                })
+            })
+        };
+
+        let mut diagnostics = Vec::new();
+        let mut unhandled = Vec::new();
+        // Summary messages like "aborting due to previous error" have no spans
+        for message in build.messages.iter().filter(|m| !m.spans.is_empty()) {
+            if is_planned(message) {
+                diagnostics.push(Diagnostic::from(message));
+            } else {
+                unhandled.push(Diagnostic::from(message));
+            }
+        }
+
+        Iteration {
+            fixes,
+            diagnostics,
+            unhandled,
+            edits,
+            modified_files,
+            conflicts,
+            declined,
+            stale,
+            unrelated_names,
+            api_changes,
+        }
+    }
+
+    fn cargo(&self) -> Command {
+        let mut command = Command::new("cargo");
+        if let Some(current_dir) = &self.current_dir {
+            command.current_dir(current_dir);
+        }
+        if let Some(target_dir) = &self.target_dir {
+            command.env("CARGO_TARGET_DIR", target_dir);
+        }
+        command
+    }
+}
+
+/// The compiler messages from one check build of the workspace.
+#[derive(Debug)]
+struct Build {
+    messages: Vec<Message>,
+    workspace_root: PathBuf,
+    target_dir: PathBuf,
+}
+
+/// What may change in each file after one build.
+#[derive(Debug)]
+struct FixPlan<'a> {
+    /// Keyed by the file name relative to the workspace root.
+    files: BTreeMap<String, FilePlan<'a>>,
+
+    /// The message that first reported each range of a file.
+    messages: BTreeMap<(&'a str, (usize, usize)), &'a Message>,
+
+    /// The compiler's suggestions for each file.
+    suggestions: BTreeMap<&'a str, edit::Suggestions<'a>>,
+
+    insertions: Insertions<'a>,
+    scan: &'a Scan,
+}
+
+/// What may change in one file.
+#[derive(Debug, Default)]
+struct FilePlan<'a> {
+    /// The reported code and a fixer that may change it, in the order
+    /// the fixers should be tried.
+    spans: Vec<(&'a dyn Fixer, &'a Span)>,
+
+    /// The reported code with a machine-applicable compiler
+    /// suggestion.
+    compiler_suggestions: Vec<&'a Span>,
+
+    /// Code added next to error types.
+    insertions: Vec<Category<Edit>>,
+}
+
+/// What changed in each file after one build.
+#[derive(Debug, Default)]
+struct Applied {
+    fixes: FileMapping,
+    edits: BTreeMap<PathBuf, Vec<Category<Edit>>>,
+    modified_files: BTreeMap<PathBuf, FileChange>,
+    conflicts: BTreeMap<PathBuf, Vec<Conflict>>,
+    declined: Vec<Category<Location>>,
+    stale: Vec<Location>,
+    unrelated_names: Vec<UnrelatedName>,
+}
@@ -1,185 +1,107 @@
 use argh::FromArgs;
-use once_cell::sync::Lazy;
-use regex::Regex;
-use serde::Deserialize;
-use std::{
-    borrow::Cow,
-    collections::{BTreeMap, BTreeSet},
-    env, fs,
-    path::PathBuf,
-    process::{self, Command},
+use snafu_upgrade_assistant::{
+    workspace_root, AcceptAll, Config, Interactive, Migration, Migrator, Options, Outcome,
+    Progress, Result, Review, Rule,
 };
+use std::{collections::BTreeSet, fs, path::PathBuf, process};
 
-type Error = Box<dyn std::error::Error>;
-type Result<T, E = Error> = std::result::Result<T, E>;
-
-#[derive(Debug, Deserialize)]
-#[serde(tag = "reason")]
-enum Line {
-    #[serde(rename = "compiler-message")]
-    CompilerMessage { message: Message },
-
-    #[serde(other)]
-    Other,
-}
-
-#[derive(Debug, Deserialize)]
-struct Message {
-    code: Option<Code>,
-    spans: Vec<Span>,
-}
-
-impl Message {
-    fn categorize<T>(&self, v: T) -> Option<Category<T>> {
-        match &self.code {
-            Some(code) => {
-                if code.is_context_selector_rename() {
-                    Some(Category::ContextSelectorRename(v))
-                } else if code.is_with_context_argument() {
-                    Some(Category::WithContextArgument(v))
-                } else {
-                    None
-                }
-            }
-            None => {
-                if self.is_attribute_equal() {
-                    Some(Category::EqualSyntax(v))
-                } else {
-                    None
-                }
-            }
-        }
-    }
-
-    fn is_attribute_equal(&self) -> bool {
-        static LOOKS_LIKE_ATTRIBUTE_ERROR: Lazy<Regex> =
-            Lazy::new(|| Regex::new(r#"snafu\(.*="#).unwrap());
-
-        self.spans.iter().any(|s| {
-            s.text
-                .iter()
-                .any(|t| LOOKS_LIKE_ATTRIBUTE_ERROR.is_match(&t.text))
-        })
-    }
-}
-
-#[derive(Debug, Deserialize)]
-struct Code {
-    code: String,
-}
+/// Helps upgrade SNAFU between semver-incompatible versions
+#[derive(Debug, FromArgs)]
+struct Opts {
+    #[argh(subcommand)]
+    command: Option<Subcommand>,
 
-impl Code {
-    fn is_context_selector_rename(&self) -> bool {
-        let error_codes = &["E0412", "E0422", "E0423", "E0425", "E0432", "E0574"];
+    /// show version information
+    #[argh(switch)]
+    version: bool,
 
-        error_codes.iter().any(|&c| c == self.code)
-    }
+    /// do not write changes to disk; print a diff of the changes
+    /// instead
+    #[argh(switch)]
+    dry_run: bool,
 
-    fn is_with_context_argument(&self) -> bool {
-        let error_codes = &["E0593"];
-        error_codes.iter().any(|&c| c == self.code)
-    }
-}
+    /// ask before applying each fix
+    #[argh(switch)]
+    interactive: bool,
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum Category<T> {
-    ContextSelectorRename(T),
-    WithContextArgument(T),
-    EqualSyntax(T),
-}
+    /// also apply the compiler's machine-applicable suggestions
+    #[argh(switch)]
+    apply_compiler_suggestions: bool,
 
-impl<T> Category<T> {
-    fn as_ref(&self) -> Category<&T> {
-        use Category::*;
+    /// do not apply the compiler's suggestions, even if the
+    /// configuration enables it
+    #[argh(switch)]
+    no_apply_compiler_suggestions: bool,
 
-        match self {
-            ContextSelectorRename(v) => ContextSelectorRename(v),
-            WithContextArgument(v) => WithContextArgument(v),
-            EqualSyntax(v) => EqualSyntax(v),
-        }
-    }
+    /// keep the SNAFU 0.6 context selector names by adding
+    /// `#[snafu(context(suffix(false)))]` to the error enums instead of
+    /// renaming every use
+    #[argh(switch)]
+    preserve_names: bool,
 
-    fn unify(self) -> T {
-        use Category::*;
+    /// rename the context selectors, even if the configuration
+    /// preserves their names
+    #[argh(switch)]
+    no_preserve_names: bool,
 
-        match self {
-            ContextSelectorRename(v) => v,
-            WithContextArgument(v) => v,
-            EqualSyntax(v) => v,
-        }
-    }
+    /// add a deprecated alias of each renamed `pub` context
+    /// selector under its old name, so users of a library keep
+    /// compiling
+    #[argh(switch)]
+    deprecated_aliases: bool,
 
-    fn map<U>(self, f: impl FnOnce(T) -> U) -> Category<U> {
-        use Category::*;
+    /// do not add deprecated aliases, even if the configuration
+    /// enables them
+    #[argh(switch)]
+    no_deprecated_aliases: bool,
 
-        match self {
-            ContextSelectorRename(v) => ContextSelectorRename(f(v)),
-            WithContextArgument(v) => WithContextArgument(f(v)),
-            EqualSyntax(v) => EqualSyntax(f(v)),
-        }
-    }
-}
+    /// check that the workspace builds, then upgrade every SNAFU 0.6
+    /// dependency in the workspace's Cargo.toml files to 0.7 and
+    /// update the lockfile
+    #[argh(switch)]
+    upgrade_dependency: bool,
 
-impl<T> IntoIterator for Category<T>
-where
-    T: IntoIterator,
-{
-    type Item = Category<T::Item>;
-    type IntoIter = std::iter::Map<T::IntoIter, fn(T::Item) -> Category<T::Item>>;
+    /// do not change the SNAFU dependency, even if the configuration
+    /// enables it
+    #[argh(switch)]
+    no_upgrade_dependency: bool,
 
-    fn into_iter(self) -> Self::IntoIter {
-        use Category::*;
+    /// restore every changed file if a resolution cannot be found
+    #[argh(switch)]
+    rollback_on_failure: bool,
 
-        match self {
-            ContextSelectorRename(v) => v.into_iter().map(ContextSelectorRename),
-            WithContextArgument(v) => v.into_iter().map(WithContextArgument),
-            EqualSyntax(v) => v.into_iter().map(EqualSyntax),
-        }
-    }
-}
+    /// keep the changed files if a resolution cannot be found, even if
+    /// the configuration enables rolling back
+    #[argh(switch)]
+    no_rollback_on_failure: bool,
 
-impl<T> PartialOrd for Category<T>
-where
-    T: PartialOrd,
-{
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.as_ref().unify().partial_cmp(other.as_ref().unify())
-    }
-}
+    /// make changes even if the git repository has uncommitted
+    /// changes
+    #[argh(switch)]
+    allow_dirty: bool,
 
-impl<T> Ord for Category<T>
-where
-    T: Ord,
-{
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.as_ref().unify().cmp(other.as_ref().unify())
-    }
-}
+    /// create a git commit after each iteration
+    #[argh(switch)]
+    commit_each_iteration: bool,
 
-#[derive(Debug, Deserialize, PartialOrd, Ord, PartialEq, Eq)]
-struct Span {
-    byte_start: usize,
-    byte_end: usize,
-    file_name: String,
-    is_primary: bool,
-    text: Vec<Text>,
-}
+    /// do not create git commits, even if the configuration enables
+    /// them
+    #[argh(switch)]
+    no_commit_each_iteration: bool,
 
-#[derive(Debug, Deserialize, PartialOrd, Ord, PartialEq, Eq)]
-struct Text {
-    text: String,
-}
+    /// create and switch to a new git branch before making changes
+    #[argh(option)]
+    branch: Option<String>,
 
-/// Helps upgrade SNAFU between semver-incompatible versions
-#[derive(Debug, FromArgs)]
-struct Opts {
-    /// show version information
-    #[argh(switch)]
-    version: bool,
+    /// write a report of every change. Use `json=<path>` to write
+    /// JSON to a file.
+    #[argh(option, from_str_fn(parse_report))]
+    report: Option<Report>,
 
-    /// do not write changes to disk
-    #[argh(switch)]
-    dry_run: bool,
+    /// a TOML file of extra rewrite rules to apply. The option may be
+    /// used multiple times.
+    #[argh(option)]
+    rules: Vec<PathBuf>,
 
     /// extra arguments to `cargo check`. The option may be used
     /// multiple times.
@@ -187,250 +109,323 @@ struct Opts {
     extra_check_arg: Vec<String>,
 
     /// what context selector suffix to use. Defaults to "Snafu"
-    #[argh(option, default = "Self::DEFAULT_SUFFIX.to_string()")]
-    suffix: String,
+    #[argh(option)]
+    suffix: Option<String>,
+
+    /// what context selector suffix to use for one error enum, as
+    /// `<path>=<suffix>`, such as `db::Error=Context`. An empty suffix
+    /// keeps the variant names. The option may be used multiple times.
+    #[argh(option, from_str_fn(parse_type_suffix))]
+    type_suffix: Vec<(String, String)>,
 
     /// what directory to make changes in. Defaults to the workspace
     /// root
-    #[argh(option, default = "workspace_root().unwrap()")]
-    directory: PathBuf,
+    #[argh(option)]
+    directory: Option<PathBuf>,
 
-    /// how many iterations to perform before giving up
-    #[argh(option, default = "Self::DEFAULT_MAXIMUM_ITERATIONS")]
-    max_iterations: usize,
+    /// how many iterations to perform before giving up. Defaults to 5
+    #[argh(option)]
+    max_iterations: Option<usize>,
 
     /// show detailed information
     #[argh(switch)]
     verbose: bool,
 }
 
-impl Opts {
-    const DEFAULT_SUFFIX: &'static str = "Snafu";
-    const DEFAULT_MAXIMUM_ITERATIONS: usize = 5;
+#[derive(Debug)]
+enum Report {
+    Json(PathBuf),
 }
 
-fn main() -> Result<()> {
-    let opts: Opts = argh::from_env();
-
-    if opts.version {
-        println!(env!("CARGO_PKG_VERSION"));
-        return Ok(());
+fn parse_report(value: &str) -> Result<Report, String> {
+    match value.split_once('=') {
+        Some(("json", path)) if !path.is_empty() => Ok(Report::Json(path.into())),
+        _ => Err(format!(
+            "Unknown report `{}`; expected `json=<path>`",
+            value
+        )),
     }
+}
 
-    eprintln!("Performing initial check build; this may take a while");
+fn parse_type_suffix(value: &str) -> Result<(String, String), String> {
+    let (path, suffix) = value.split_once('=').ok_or_else(|| {
+        format!(
+            "Unknown type suffix `{}`; expected `<path>=<suffix>`",
+            value
+        )
+    })?;
+    Ok((path.into(), suffix.into()))
+}
 
-    let mut depth = 0;
-    let mut last_fix = apply_once(&opts)?;
+#[derive(Debug, FromArgs)]
+#[argh(subcommand)]
+enum Subcommand {
+    Undo(UndoOpts),
+}
 
-    if opts.dry_run {
-        return Ok(());
+/// restore the files changed by the last run
+#[derive(Debug, FromArgs)]
+#[argh(subcommand, name = "undo")]
+struct UndoOpts {}
+
+/// Combines a switch and its `--no-` form with the project's
+/// configuration.
+fn switch(name: &str, on: bool, off: bool, config: Option<bool>) -> Result<bool> {
+    match (on, off) {
+        (true, true) => Err(format!("`--{0}` and `--no-{0}` cannot be used together", name).into()),
+        (true, false) => Ok(true),
+        (false, true) => Ok(false),
+        (false, false) => Ok(config.unwrap_or(false)),
     }
+}
 
-    loop {
-        if opts.verbose {
-            dbg!(depth);
-        }
-
-        if last_fix.is_empty() {
-            break;
-        }
-
-        if depth > opts.max_iterations {
-            eprintln!(
-                "Could not converge on a resolution in {} attempts",
-                opts.max_iterations
-            );
-            process::exit(1);
-        }
-
-        eprintln!("Performing follow-up check build");
-        let current_fix = apply_once(&opts)?;
-
-        if last_fix == current_fix {
-            eprintln!("Did not make progress on a resolution");
-            process::exit(1);
-        }
-
-        last_fix = current_fix;
-        depth += 1;
+impl Opts {
+    /// Combines the command line options with the project's
+    /// configuration. Options from the command line take precedence.
+    fn into_options(self, config: Config, workspace_root: PathBuf) -> Result<Options> {
+        let directory = self
+            .directory
+            .or(config.directory)
+            .unwrap_or(workspace_root);
+
+        let mut options = Options::new(directory);
+        options.dry_run = self.dry_run;
+        options.extra_check_arg = match self.extra_check_arg {
+            args if args.is_empty() => config.extra_check_arg.unwrap_or_default(),
+            args => args,
+        };
+        options.suffix = self
+            .suffix
+            .or(config.suffix)
+            .unwrap_or_else(|| Options::DEFAULT_SUFFIX.into());
+        options.type_suffixes = config.type_suffix.unwrap_or_default();
+        options.type_suffixes.extend(self.type_suffix);
+        options.preserve_names = switch(
+            "preserve-names",
+            self.preserve_names,
+            self.no_preserve_names,
+            config.preserve_names,
+        )?;
+        options.deprecated_aliases = switch(
+            "deprecated-aliases",
+            self.deprecated_aliases,
+            self.no_deprecated_aliases,
+            config.deprecated_aliases,
+        )?;
+        options.max_iterations = self
+            .max_iterations
+            .or(config.max_iterations)
+            .unwrap_or(Options::DEFAULT_MAXIMUM_ITERATIONS);
+        options.apply_compiler_suggestions = switch(
+            "apply-compiler-suggestions",
+            self.apply_compiler_suggestions,
+            self.no_apply_compiler_suggestions,
+            config.apply_compiler_suggestions,
+        )?;
+        options.rollback_on_failure = switch(
+            "rollback-on-failure",
+            self.rollback_on_failure,
+            self.no_rollback_on_failure,
+            config.rollback_on_failure,
+        )?;
+        options.allow_dirty = self.allow_dirty;
+        options.commit_each_iteration = switch(
+            "commit-each-iteration",
+            self.commit_each_iteration,
+            self.no_commit_each_iteration,
+            config.commit_each_iteration,
+        )?;
+        options.branch = self.branch.or(config.branch);
+        options.upgrade_dependency = switch(
+            "upgrade-dependency",
+            self.upgrade_dependency,
+            self.no_upgrade_dependency,
+            config.upgrade_dependency,
+        )?;
+        options.verbose = self.verbose;
+        Ok(options)
     }
-
-    Ok(())
 }
 
-type FileMapping = BTreeMap<String, Vec<Category<(usize, usize)>>>;
-
-fn apply_once(opts: &Opts) -> Result<FileMapping> {
-    let mut build_command = Command::new("cargo");
-    build_command.arg("check");
-    for arg in &opts.extra_check_arg {
-        build_command.arg(arg);
-    }
-    build_command.args(&["--message-format", "json"]);
+fn main() -> Result<()> {
+    let mut opts: Opts = argh::from_env();
 
-    if opts.verbose {
-        dbg!(&build_command);
+    if opts.version {
+        println!(env!("CARGO_PKG_VERSION"));
+        return Ok(());
     }
 
-    let output = build_command.output()?;
-    let stdout = String::from_utf8(output.stdout)?;
+    let workspace_root = workspace_root()?;
 
-    if opts.verbose {
-        dbg!(&stdout);
+    if let Some(Subcommand::Undo(_)) = opts.command {
+        let migrator = Migrator::new(opts.into_options(Config::default(), workspace_root)?);
+        for path in migrator.undo()? {
+            eprintln!("Restored {}", path.display());
+        }
+        return Ok(());
     }
 
-    let lines: Vec<Line> = stdout
-        .lines()
-        .map(|l| serde_json::from_str(l))
-        .collect::<Result<_, _>>()?;
-
-    if opts.verbose {
-        dbg!(&lines);
+    let mut review: Box<dyn Review> = if opts.interactive {
+        Box::new(Interactive::default())
+    } else {
+        Box::new(AcceptAll)
+    };
+
+    let mut config = Config::load(&workspace_root)?.unwrap_or_default();
+
+    let report = opts.report.take();
+    let rules = match std::mem::take(&mut opts.rules) {
+        rules if rules.is_empty() => config.rules.take().unwrap_or_default(),
+        rules => rules,
+    };
+
+    let mut migrator = Migrator::new(opts.into_options(config, workspace_root)?);
+    migrator.on_progress(print_progress);
+    for path in rules {
+        for rule in Rule::from_file(&path)? {
+            migrator.add_fixer(rule);
+        }
     }
+    let migration = migrator.run_with_review(&mut *review)?;
 
-    let relevant_spans: BTreeSet<_> = lines
-        .iter()
-        .flat_map(|l| match l {
-            Line::CompilerMessage { message } => Some(message),
-            Line::Other => None,
-        })
-        .flat_map(|m| m.categorize(m))
-        .flat_map(|c| c.map(|m| &m.spans))
-        .filter(|c| match c {
-            Category::ContextSelectorRename(v) => v.is_primary,
-            // The secondary error message points to the closure argument
-            Category::WithContextArgument(v) => !v.is_primary,
-            Category::EqualSyntax(v) => v.is_primary,
-        })
-        .collect();
-
-    if opts.verbose {
-        dbg!(&relevant_spans);
+    if let Some(Report::Json(path)) = report {
+        fs::write(path, migration.json_report()?)?;
     }
 
-    let mut file_mapping: FileMapping = BTreeMap::new();
-    for &span in &relevant_spans {
-        let file_name = &span.as_ref().unify().file_name;
-        let range = span.as_ref().map(|s| (s.byte_start, s.byte_end));
-        file_mapping
-            .entry(file_name.to_owned())
-            .or_default()
-            .push(range);
-    }
+    let directory = &migrator.options().directory;
 
-    if opts.verbose {
-        dbg!(&file_mapping);
+    if migrator.options().dry_run {
+        for (path, change) in &migration.changes() {
+            let path = path.strip_prefix(directory).unwrap_or(path);
+            print!("{}", change.unified_diff(path));
+        }
     }
 
-    let workspace_root = workspace_root()?;
+    // Declined edits are planned again by every iteration
+    let mut declined = BTreeSet::new();
+
+    for iteration in &migration.iterations {
+        for (path, conflicts) in &iteration.conflicts {
+            let path = path.strip_prefix(directory).unwrap_or(path);
+            for conflict in conflicts {
+                eprintln!(
+                    "{}:{}:{}: skipped a {} because it overlaps with a {}",
+                    path.display(),
+                    conflict.line,
+                    conflict.column,
+                    conflict.edit.name(),
+                    conflict.conflicts_with.name(),
+                );
+            }
+        }
 
-    for (filename, spans) in &mut file_mapping {
-        let filename = workspace_root.join(filename);
-        if !filename.starts_with(&opts.directory) {
-            return Err(format!(
-                "Attempted to update file outside of safe directory. {} is not within {}",
-                filename.display(),
-                opts.directory.display(),
-            )
-            .into());
+        for edit in &iteration.declined {
+            let location = &edit.value;
+            if declined.insert((&location.file, location.start, edit.name())) {
+                eprintln!(
+                    "{}:{}:{}: skipped a {} because it was declined",
+                    location.file,
+                    location.line,
+                    location.column,
+                    edit.name(),
+                );
+            }
         }
 
-        if opts.verbose {
-            dbg!(&filename);
+        for location in &iteration.stale {
+            eprintln!(
+                "{}:{}:{}: skipped a fix because the file changed after it was checked",
+                location.file, location.line, location.column,
+            );
         }
+    }
 
-        let content = fs::read_to_string(&filename)?;
-        let mut content: &str = &content;
-
-        let mut pieces: Vec<Cow<str>> = Vec::new();
-
-        for cat in spans.iter().copied().rev() {
-            match cat {
-                Category::ContextSelectorRename((_start, end)) => {
-                    let (head, tail) = content.split_at(end);
-
-                    if opts.verbose {
-                        dbg!(&head[head.len() - 10..]);
-                        dbg!(&tail[..10]);
-                    }
-
-                    // Assume we've already applied the suffix and avoid adding it again
-                    if head.ends_with(&opts.suffix) {
-                        continue;
-                    }
-
-                    let head = head.strip_suffix("Error").unwrap_or(head);
-                    let head = head.strip_suffix("Context").unwrap_or(head);
-
-                    pieces.push(Cow::Borrowed(tail));
-                    pieces.push(Cow::Borrowed(&opts.suffix));
-                    content = head;
-                }
-                Category::WithContextArgument((_start, end)) => {
-                    // The range points to the `||` in the closure
-                    // arguments, so we offset by one to get into the
-                    // middle
-                    let (head, tail) = content.split_at(end - 1);
-
-                    if opts.verbose {
-                        dbg!(&head[head.len() - 10..]);
-                        dbg!(&tail[..10]);
-                    }
-
-                    pieces.push(Cow::Borrowed(tail));
-                    pieces.push(Cow::Borrowed("_"));
-                    content = head;
-                }
-                Category::EqualSyntax((start, _end)) => {
-                    let (head, _tail) = content.split_at(start);
-                    // Skim backwards to find spaces
-                    let (head, tail) = content.split_at(head.trim_end().len());
-
-                    static REWRITE_EQUAL_ATTRIBUTE: Lazy<Regex> =
-                        Lazy::new(|| Regex::new(r#"\s*=\s*"([^"]+)""#).unwrap());
-
-                    let attribute = REWRITE_EQUAL_ATTRIBUTE
-                        .replace(tail, |cap: &regex::Captures| {
-                            format!(r#"({})"#, cap.get(1).unwrap().as_str())
-                        });
-
-                    pieces.push(attribute);
-                    content = head;
-                }
+    if let Some(last) = migration.iterations.last() {
+        if !last.unrelated_names.is_empty() {
+            eprintln!("These names could not be found, but are not SNAFU context selectors:");
+            for name in &last.unrelated_names {
+                eprintln!(
+                    "  {}:{}:{}: `{}`",
+                    name.file.display(),
+                    name.line,
+                    name.column,
+                    name.name
+                );
             }
         }
-        pieces.push(Cow::Borrowed(content));
+    }
+
+    let summary = migration.summary();
 
-        if opts.verbose {
-            dbg!(spans.len(), pieces.len());
+    for error in &summary.remaining_errors {
+        if let Some(rendered) = &error.rendered {
+            eprintln!();
+            eprintln!("{}", rendered.trim_end());
         }
+    }
 
-        let modified_content: String = pieces.into_iter().rev().collect();
+    eprintln!();
+    eprint!("{}", summary);
 
-        if opts.verbose {
-            dbg!(&modified_content);
-        }
+    let semver_impact = migration.semver_impact();
+    if !semver_impact.changes.is_empty() {
+        eprintln!();
+        eprint!("{}", semver_impact);
+    }
 
-        if opts.dry_run {
-            eprintln!("Would write modified content to '{}'", filename.display());
-        } else {
-            fs::write(&filename, modified_content)?;
+    match migration.outcome {
+        Outcome::Converged => {}
+        Outcome::IterationLimit => {
+            eprintln!(
+                "Could not converge on a resolution in {} attempts",
+                migrator.options().max_iterations
+            );
+            report_failure(&migration);
+            process::exit(1);
+        }
+        Outcome::NoProgress => {
+            eprintln!("Did not make progress on a resolution");
+            report_failure(&migration);
+            process::exit(1);
         }
     }
 
-    Ok(file_mapping)
+    Ok(())
 }
 
-fn workspace_root() -> Result<PathBuf> {
-    let output = Command::new("cargo")
-        .args(&["metadata", "--format-version", "1"])
-        .output()?;
-    let metadata: Metadata = serde_json::from_slice(&output.stdout)?;
+fn report_failure(migration: &Migration) {
+    if let Some(last) = migration.iterations.last() {
+        eprintln!(
+            "Iteration {} was blocked by these diagnostics:",
+            migration.iterations.len(),
+        );
+        for diagnostic in &last.diagnostics {
+            eprintln!("  {}", diagnostic);
+        }
+    }
 
-    Ok(metadata.workspace_root.into())
+    for path in &migration.rolled_back {
+        eprintln!("Restored {}", path.display());
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct Metadata {
-    workspace_root: String,
+fn print_progress(step: Progress<'_>) {
+    match step {
+        Progress::InitialBuild => {
+            eprintln!("Performing initial check build; this may take a while")
+        }
+        Progress::FollowUpBuild => eprintln!("Performing follow-up check build"),
+        Progress::UpgradedDependency { manifest } => {
+            eprintln!("Upgraded SNAFU in {}", manifest.display())
+        }
+        Progress::SkippedDependency {
+            manifest,
+            directory,
+        } => eprintln!(
+            "Did not upgrade SNAFU in {} because it is not within {}",
+            manifest.display(),
+            directory.display(),
+        ),
+        _ => {}
+    }
 }
@@ -0,0 +1,394 @@
+use crate::Result;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+/// The tables of a manifest that list dependencies, at the top level
+/// or inside of a `[target.'cfg(...)']` table.
+const DEPENDENCY_TABLES: &[&str] = &[
+    "dependencies",
+    "dev-dependencies",
+    "dev_dependencies",
+    "build-dependencies",
+    "build_dependencies",
+];
+
+/// Replaces every requirement on SNAFU 0.6 in the Cargo manifest
+/// `content` with `requirement`, including renamed dependencies and
+/// `[workspace.dependencies]`. Formatting and comments are preserved.
+/// Returns `None` when nothing needs to change.
+pub(crate) fn upgrade_snafu(content: &str, requirement: &str) -> Result<Option<String>> {
+    let mut manifest: DocumentMut = content.parse()?;
+    let mut changed = false;
+
+    for_each_dependency_table(&mut manifest, &mut |table| {
+        changed |= upgrade_table(table, requirement);
+    });
+
+    Ok(changed.then(|| manifest.to_string()))
+}
+
+/// Makes every `path` dependency of the Cargo manifest `content` in
+/// `manifest_dir` that is outside of `workspace_root` absolute, so
+/// that it still resolves from a copy of the workspace. This includes
+/// `[patch]` tables. Returns `None` when nothing needs to change.
+pub(crate) fn absolute_external_paths(
+    content: &str,
+    manifest_dir: &Path,
+    workspace_root: &Path,
+) -> Result<Option<String>> {
+    let mut manifest: DocumentMut = content.parse()?;
+    let mut changed = false;
+
+    let mut make_absolute = |table: &mut dyn TableLike| {
+        for (_, dependency) in table.iter_mut() {
+            let path = match dependency
+                .as_table_like_mut()
+                .and_then(|d| d.get_mut("path"))
+                .and_then(Item::as_value_mut)
+            {
+                Some(path) => path,
+                None => continue,
+            };
+
+            let absolute = match path.as_str() {
+                Some(relative) => manifest_dir.join(relative),
+                None => continue,
+            };
+            // Paths reported by Cargo are canonical, so ours should be as well
+            let absolute = fs::canonicalize(&absolute).unwrap_or_else(|_| normalize(&absolute));
+            if absolute.starts_with(workspace_root) {
+                continue;
+            }
+
+            let decor = path.decor().clone();
+            *path = Value::from(absolute.to_string_lossy().into_owned());
+            *path.decor_mut() = decor;
+            changed = true;
+        }
+    };
+
+    for_each_dependency_table(&mut manifest, &mut make_absolute);
+    if let Some(patches) = manifest.get_mut("patch").and_then(Item::as_table_like_mut) {
+        for (_, table) in patches.iter_mut() {
+            if let Some(table) = table.as_table_like_mut() {
+                make_absolute(table);
+            }
+        }
+    }
+
+    Ok(changed.then(|| manifest.to_string()))
+}
+
+/// Removes the `.` and `..` components of `path` without looking at
+/// the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Calls `f` with every table of `manifest` that lists dependencies.
+fn for_each_dependency_table(manifest: &mut DocumentMut, f: &mut dyn FnMut(&mut dyn TableLike)) {
+    for &name in DEPENDENCY_TABLES {
+        if let Some(table) = manifest.get_mut(name).and_then(Item::as_table_like_mut) {
+            f(table);
+        }
+    }
+
+    if let Some(targets) = manifest.get_mut("target").and_then(Item::as_table_like_mut) {
+        for (_, target) in targets.iter_mut() {
+            for &name in DEPENDENCY_TABLES {
+                if let Some(table) = target.get_mut(name).and_then(Item::as_table_like_mut) {
+                    f(table);
+                }
+            }
+        }
+    }
+
+    if let Some(table) = manifest
+        .get_mut("workspace")
+        .and_then(|w| w.get_mut("dependencies"))
+        .and_then(Item::as_table_like_mut)
+    {
+        f(table);
+    }
+}
+
+fn upgrade_table(table: &mut dyn TableLike, requirement: &str) -> bool {
+    let mut changed = false;
+
+    for (key, dependency) in table.iter_mut() {
+        let is_snafu = dependency
+            .as_table_like()
+            .and_then(|d| d.get("package"))
+            .and_then(Item::as_str)
+            .unwrap_or_else(|| key.get())
+            == "snafu";
+        if !is_snafu {
+            continue;
+        }
+
+        // Either `snafu = "0.6"` or `snafu = { version = "0.6", ... }`
+        let version = match dependency.as_table_like_mut() {
+            Some(dependency) => dependency.get_mut("version").and_then(Item::as_value_mut),
+            None => dependency.as_value_mut(),
+        };
+
+        if let Some(version) = version {
+            if version.as_str().is_some_and(is_snafu_0_6) {
+                let decor = version.decor().clone();
+                *version = Value::from(requirement);
+                *version.decor_mut() = decor;
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+/// Whether the version requirement only allows SNAFU 0.6, such as
+/// `0.6`, `^0.6.10`, or `=0.6.3`.
+fn is_snafu_0_6(requirement: &str) -> bool {
+    let first = requirement.split(',').next().unwrap_or_default();
+    let version = first.trim_start_matches(|c: char| "^=~>< ".contains(c));
+
+    version == "0.6" || version.starts_with("0.6.")
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Lockfile {
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+}
+
+/// The versions of SNAFU 0.6 locked by the `Cargo.lock` `content`.
+pub(crate) fn locked_snafu_0_6(content: &str) -> Result<Vec<String>> {
+    let lockfile: Lockfile = toml::from_str(content)?;
+
+    Ok(lockfile
+        .package
+        .into_iter()
+        .filter(|p| p.name == "snafu" && is_snafu_0_6(&p.version))
+        .map(|p| p.version)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_plain_and_detailed_requirements() {
+        let manifest = r#"
+[dependencies]
+snafu = "0.6"
+
+[dev-dependencies]
+snafu = { version = "^0.6.10", features = ["backtraces"] }
+"#;
+
+        let upgraded = upgrade_snafu(manifest, "0.7").unwrap().unwrap();
+
+        assert_eq!(
+            upgraded,
+            r#"
+[dependencies]
+snafu = "0.7"
+
+[dev-dependencies]
+snafu = { version = "0.7", features = ["backtraces"] }
+"#,
+        );
+    }
+
+    #[test]
+    fn upgrades_workspace_dependencies() {
+        let manifest = r#"
+[workspace]
+members = ["a"]
+
+[workspace.dependencies]
+snafu = "0.6.3"
+"#;
+
+        let upgraded = upgrade_snafu(manifest, "0.7").unwrap().unwrap();
+
+        assert!(upgraded.contains(r#"snafu = "0.7""#), "{}", upgraded);
+    }
+
+    #[test]
+    fn upgrades_target_specific_dependencies() {
+        let manifest = r#"
+[target.'cfg(unix)'.dev-dependencies]
+snafu = { version = "0.6" }
+"#;
+
+        let upgraded = upgrade_snafu(manifest, "0.7").unwrap().unwrap();
+
+        assert_eq!(
+            upgraded,
+            r#"
+[target.'cfg(unix)'.dev-dependencies]
+snafu = { version = "0.7" }
+"#,
+        );
+    }
+
+    #[test]
+    fn upgrades_renamed_dependencies() {
+        let manifest = r#"
+[dependencies]
+errors = { package = "snafu", version = "0.6" }
+not-snafu = "0.6"
+"#;
+
+        let upgraded = upgrade_snafu(manifest, "0.7").unwrap().unwrap();
+
+        assert_eq!(
+            upgraded,
+            r#"
+[dependencies]
+errors = { package = "snafu", version = "0.7" }
+not-snafu = "0.6"
+"#,
+        );
+    }
+
+    #[test]
+    fn preserves_formatting_and_comments() {
+        let manifest = r#"
+[dependencies]
+# Errors
+snafu   =   "0.6"   # keep in sync
+serde = "1"
+"#;
+
+        let upgraded = upgrade_snafu(manifest, "0.7").unwrap().unwrap();
+
+        assert_eq!(
+            upgraded,
+            r#"
+[dependencies]
+# Errors
+snafu   =   "0.7"   # keep in sync
+serde = "1"
+"#,
+        );
+    }
+
+    #[test]
+    fn leaves_other_requirements_alone() {
+        let manifest = r#"
+[dependencies]
+snafu = "0.7"
+
+[dev-dependencies]
+snafu = { workspace = true }
+"#;
+
+        assert_eq!(upgrade_snafu(manifest, "0.7").unwrap(), None);
+    }
+
+    #[test]
+    fn makes_external_paths_absolute() {
+        let manifest = r#"
+[dependencies]
+shared = { path = "../../shared" } # outside
+sibling = { path = "../sibling", version = "1" }
+snafu = "0.6"
+
+[patch.crates-io]
+serde = { path = "../../vendor/serde" }
+"#;
+
+        let workspace_root = Path::new("/nonexistent/workspace");
+        let modified =
+            absolute_external_paths(manifest, &workspace_root.join("app"), workspace_root)
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(
+            modified,
+            r#"
+[dependencies]
+shared = { path = "/nonexistent/shared" } # outside
+sibling = { path = "../sibling", version = "1" }
+snafu = "0.6"
+
+[patch.crates-io]
+serde = { path = "/nonexistent/vendor/serde" }
+"#,
+        );
+    }
+
+    #[test]
+    fn leaves_paths_inside_of_the_workspace_alone() {
+        let manifest = r#"
+[workspace.dependencies]
+core = { path = "crates/core" }
+
+[target.'cfg(unix)'.dependencies]
+unix = { path = "./crates/unix" }
+"#;
+
+        let workspace_root = Path::new("/nonexistent/workspace");
+        let modified = absolute_external_paths(manifest, workspace_root, workspace_root).unwrap();
+
+        assert_eq!(modified, None);
+    }
+
+    #[test]
+    fn recognizes_snafu_0_6_requirements() {
+        for requirement in ["0.6", "0.6.10", "^0.6", "=0.6.3", "~0.6.1", ">=0.6.2, <0.7"] {
+            assert!(is_snafu_0_6(requirement), "{}", requirement);
+        }
+
+        for requirement in ["0.7", "0.60", "1.0.6", "*", ">=0.5, <0.7"] {
+            assert!(!is_snafu_0_6(requirement), "{}", requirement);
+        }
+    }
+
+    #[test]
+    fn finds_locked_snafu_0_6() {
+        let lockfile = r#"
+version = 3
+
+[[package]]
+name = "snafu"
+version = "0.6.10"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "snafu"
+version = "0.7.5"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "snafu-derive"
+version = "0.6.10"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+        assert_eq!(locked_snafu_0_6(lockfile).unwrap(), ["0.6.10"]);
+        assert!(locked_snafu_0_6("version = 3").unwrap().is_empty());
+    }
+}
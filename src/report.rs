@@ -0,0 +1,203 @@
+use crate::{edit::line_column, ApiChange, Diagnostic, Location, Migration, Outcome, Result};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt, path::Path};
+
+/// A human-readable overview of a [`Migration`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary<'a> {
+    pub iterations: usize,
+    pub files_changed: usize,
+
+    /// How many edits of each kind were applied, keyed by
+    /// [`Category::name`](crate::Category::name).
+    pub edit_counts: BTreeMap<&'a str, usize>,
+
+    /// The errors reported by the final build, whether or not a fix
+    /// was planned for them, ordered by error code.
+    pub remaining_errors: Vec<&'a Diagnostic>,
+}
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Iterations: {}", self.iterations)?;
+        writeln!(f, "Files changed: {}", self.files_changed)?;
+
+        if !self.edit_counts.is_empty() {
+            writeln!(f, "Fixes applied:")?;
+            for (name, count) in &self.edit_counts {
+                writeln!(f, "  {}: {}", name, count)?;
+            }
+        }
+
+        if self.remaining_errors.is_empty() {
+            writeln!(f, "No errors remain")?;
+        } else {
+            writeln!(
+                f,
+                "Errors that need to be fixed manually: {}",
+                self.remaining_errors.len()
+            )?;
+
+            let mut code = None;
+            for error in &self.remaining_errors {
+                if code != Some(&error.code) {
+                    code = Some(&error.code);
+
+                    match &error.code {
+                        Some(code) => write!(f, "  [{}]", code)?,
+                        None => write!(f, "  [no error code]")?,
+                    }
+                    match error.hint() {
+                        Some(hint) => writeln!(f, " Hint: {}", hint)?,
+                        None => writeln!(f)?,
+                    }
+                }
+
+                write!(f, "    ")?;
+                if let Some(location) = &error.location {
+                    write!(
+                        f,
+                        "{}:{}:{}: ",
+                        location.file, location.line, location.column
+                    )?;
+                }
+                writeln!(f, "{}", error.message)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    outcome: &'static str,
+    iterations: Vec<IterationReport<'a>>,
+    rolled_back: Vec<&'a Path>,
+}
+
+#[derive(Debug, Serialize)]
+struct IterationReport<'a> {
+    /// 1-based
+    number: usize,
+    edits: Vec<EditReport<'a>>,
+    declined: Vec<DeclinedReport<'a>>,
+    unhandled: &'a [Diagnostic],
+    api_changes: &'a [ApiChange],
+}
+
+#[derive(Debug, Serialize)]
+struct DeclinedReport<'a> {
+    kind: &'a str,
+    #[serde(flatten)]
+    location: &'a Location,
+}
+
+#[derive(Debug, Serialize)]
+struct EditReport<'a> {
+    /// Relative to the workspace root
+    file: &'a Path,
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+    kind: &'a str,
+    original: &'a str,
+    replacement: &'a str,
+}
+
+impl Migration {
+    /// Summarizes the iterations, the applied fixes, and the errors
+    /// that remain.
+    pub fn summary(&self) -> Summary<'_> {
+        let mut edit_counts = BTreeMap::new();
+        for iteration in &self.iterations {
+            for (name, count) in iteration.edit_counts() {
+                *edit_counts.entry(name).or_default() += count;
+            }
+        }
+
+        let mut remaining_errors: Vec<_> = self
+            .iterations
+            .last()
+            .into_iter()
+            .flat_map(|i| i.diagnostics.iter().chain(&i.unhandled))
+            .filter(|d| d.level == "error")
+            .collect();
+        remaining_errors.sort_by(|a, b| a.code.cmp(&b.code));
+
+        Summary {
+            iterations: self.iterations.len(),
+            files_changed: self.changes().len(),
+            edit_counts,
+            remaining_errors,
+        }
+    }
+
+    /// Describes every edit made and declined in each iteration, along
+    /// with the compiler messages that were not handled, as JSON.
+    pub fn json_report(&self) -> Result<String> {
+        let iterations = self
+            .iterations
+            .iter()
+            .enumerate()
+            .map(|(i, iteration)| {
+                let mut edits = Vec::new();
+
+                for (path, file_edits) in &iteration.edits {
+                    let content = match iteration.modified_files.get(path) {
+                        Some(change) => &change.original,
+                        None => continue,
+                    };
+                    let file = path.strip_prefix(&self.workspace_root).unwrap_or(path);
+
+                    for edit in file_edits {
+                        let kind = &edit.kind.id;
+                        let edit = &edit.value;
+                        let (line, column) = line_column(content, edit.start);
+
+                        edits.push(EditReport {
+                            file,
+                            start: edit.start,
+                            end: edit.end,
+                            line,
+                            column,
+                            kind,
+                            original: edit.original(content),
+                            replacement: &edit.replacement,
+                        });
+                    }
+                }
+
+                let declined = iteration
+                    .declined
+                    .iter()
+                    .map(|d| DeclinedReport {
+                        kind: &d.kind.id,
+                        location: &d.value,
+                    })
+                    .collect();
+
+                IterationReport {
+                    number: i + 1,
+                    edits,
+                    declined,
+                    unhandled: &iteration.unhandled,
+                    api_changes: &iteration.api_changes,
+                }
+            })
+            .collect();
+
+        let report = Report {
+            outcome: match self.outcome {
+                Outcome::Converged => "converged",
+                Outcome::IterationLimit => "iteration_limit",
+                Outcome::NoProgress => "no_progress",
+            },
+            iterations,
+            rolled_back: self.rolled_back.iter().map(|p| p.as_path()).collect(),
+        };
+
+        Ok(serde_json::to_string_pretty(&report)?)
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{edit, Category, Edit, Result};
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+/// Decides which planned edits are applied to a file.
+pub trait Review {
+    /// Returns the edits that should be applied to the file at
+    /// `path`, which currently contains `content`. Edits may be
+    /// removed or have their replacement text changed.
+    fn review(
+        &mut self,
+        path: &Path,
+        content: &str,
+        edits: Vec<Category<Edit>>,
+    ) -> Result<Vec<Category<Edit>>>;
+}
+
+/// Applies every planned edit.
+#[derive(Debug, Default)]
+pub struct AcceptAll;
+
+impl Review for AcceptAll {
+    fn review(
+        &mut self,
+        _path: &Path,
+        _content: &str,
+        edits: Vec<Category<Edit>>,
+    ) -> Result<Vec<Category<Edit>>> {
+        Ok(edits)
+    }
+}
+
+/// Asks the user about each planned edit on the terminal.
+///
+/// Edits that are skipped are remembered so that the user is not
+/// asked about them again in later iterations.
+#[derive(Debug, Default)]
+pub struct Interactive {
+    skipped: BTreeSet<(PathBuf, String, String)>,
+}
+
+impl Interactive {
+    /// How many unchanged lines to show around an edit.
+    const CONTEXT_LINES: usize = 2;
+
+    fn skip_key(path: &Path, content: &str, edit: &Edit) -> (PathBuf, String, String) {
+        let (line_start, line_end) = line_bounds(content, edit);
+        (
+            path.to_owned(),
+            content[line_start..line_end].to_owned(),
+            edit.replacement.clone(),
+        )
+    }
+}
+
+impl Review for Interactive {
+    fn review(
+        &mut self,
+        path: &Path,
+        content: &str,
+        edits: Vec<Category<Edit>>,
+    ) -> Result<Vec<Category<Edit>>> {
+        let mut accepted = Vec::new();
+        let mut accept_all = false;
+
+        for edit in edits {
+            let key = Self::skip_key(path, content, &edit.value);
+            if self.skipped.contains(&key) {
+                continue;
+            }
+
+            if accept_all {
+                accepted.push(edit);
+                continue;
+            }
+
+            show(path, content, &edit, Self::CONTEXT_LINES);
+
+            loop {
+                let answer = prompt("Apply this fix? [y]es, [n]o, [e]dit, [a]ll in file: ")?;
+
+                match answer.trim() {
+                    "y" | "yes" => accepted.push(edit),
+                    "n" | "no" => {
+                        self.skipped.insert(key);
+                    }
+                    "e" | "edit" => {
+                        let original = edit.value.original(content);
+                        let replacement = prompt(&format!("Replace `{}` with: ", original))?;
+                        let replacement = replacement.trim_end_matches(&['\r', '\n'][..]);
+
+                        accepted.push(edit.map(|mut e| {
+                            e.replacement = replacement.to_owned();
+                            e
+                        }));
+                    }
+                    "a" | "all" => {
+                        accept_all = true;
+                        accepted.push(edit);
+                    }
+                    _ => continue,
+                }
+                break;
+            }
+        }
+
+        Ok(accepted)
+    }
+}
+
+fn prompt(message: &str) -> Result<String> {
+    let mut stderr = io::stderr();
+    write!(stderr, "{}", message)?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer)? == 0 {
+        return Err("Standard input was closed while waiting for an answer".into());
+    }
+
+    Ok(answer)
+}
+
+/// The byte range of the complete lines containing the edit,
+/// excluding the final newline.
+fn line_bounds(content: &str, edit: &Edit) -> (usize, usize) {
+    let line_start = content[..edit.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[edit.end..]
+        .find('\n')
+        .map_or(content.len(), |i| edit.end + i);
+
+    (line_start, line_end)
+}
+
+fn show(path: &Path, content: &str, edit: &Category<Edit>, context_lines: usize) {
+    let kind = edit.name();
+    let edit = &edit.value;
+    let (line_start, line_end) = line_bounds(content, edit);
+    let (first_line, column) = edit::line_column(content, edit.start);
+
+    let before: Vec<_> = content[..line_start]
+        .lines()
+        .rev()
+        .take(context_lines)
+        .collect();
+    let old = &content[line_start..line_end];
+    let new = format!(
+        "{}{}{}",
+        &content[line_start..edit.start],
+        edit.replacement,
+        &content[edit.end..line_end],
+    );
+    let after = content[line_end..]
+        .strip_prefix('\n')
+        .unwrap_or("")
+        .lines()
+        .take(context_lines);
+
+    eprintln!();
+    eprintln!("{}:{}:{} ({})", path.display(), first_line, column, kind);
+
+    let mut line_number = first_line - before.len();
+    for line in before.into_iter().rev() {
+        eprintln!("  {:>5} | {}", line_number, line);
+        line_number += 1;
+    }
+    for (i, line) in old.lines().enumerate() {
+        eprintln!("- {:>5} | {}", line_number + i, line);
+    }
+    for (i, line) in new.lines().enumerate() {
+        eprintln!("+ {:>5} | {}", line_number + i, line);
+    }
+    line_number += old.lines().count();
+    for line in after {
+        eprintln!("  {:>5} | {}", line_number, line);
+        line_number += 1;
+    }
+}
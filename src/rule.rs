@@ -0,0 +1,392 @@
+use crate::{
+    cargo::{Message, Span},
+    fixer::{Context, Fix, Fixer, Kind},
+    Edit, Result,
+};
+use regex::Regex;
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+/// A fix declared in a configuration file.
+///
+/// A rule applies to every compiler message with one of its error
+/// codes whose text matches its message pattern. The code pointed to
+/// by the chosen spans is replaced by the replacement template, which
+/// may refer to groups captured by the message pattern using `$name`
+/// or `${1}`.
+///
+/// ```toml
+/// [[rule]]
+/// name = "wrapper context rename"
+/// codes = ["E0425"]
+/// message = "cannot find value `(?P<name>\\w+)Ctx`"
+/// span = "primary"
+/// replacement = "${name}Snafu"
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rule {
+    name: String,
+    codes: Vec<String>,
+    message: Option<Regex>,
+    span: SpanChoice,
+    replacement: String,
+}
+
+/// Which spans of a compiler message a [`Rule`] changes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanChoice {
+    #[default]
+    Primary,
+    Secondary,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default)]
+    rule: Vec<RawRule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    name: String,
+    #[serde(default)]
+    codes: Vec<String>,
+    message: Option<String>,
+    #[serde(default)]
+    span: SpanChoice,
+    replacement: String,
+}
+
+impl Rule {
+    /// Reads every rule from the TOML file at `path`.
+    pub fn from_file(path: &Path) -> Result<Vec<Self>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read the rules in {}: {}", path.display(), e))?;
+
+        Self::from_toml(&content)
+            .map_err(|e| format!("Could not load the rules in {}: {}", path.display(), e).into())
+    }
+
+    /// Parses every rule from a TOML document.
+    pub fn from_toml(content: &str) -> Result<Vec<Self>> {
+        let file: RuleFile = toml::from_str(content)?;
+        file.rule.into_iter().map(Self::from_raw).collect()
+    }
+
+    fn from_raw(raw: RawRule) -> Result<Self> {
+        let RawRule {
+            name,
+            codes,
+            message,
+            span,
+            replacement,
+        } = raw;
+
+        if codes.is_empty() && message.is_none() {
+            return Err(format!(
+                "The rule `{}` needs error codes or a message pattern to match",
+                name
+            )
+            .into());
+        }
+
+        let message = message
+            .map(|m| Regex::new(&m))
+            .transpose()
+            .map_err(|e| format!("The rule `{}` has an invalid message pattern: {}", name, e))?;
+
+        Ok(Self {
+            name,
+            codes,
+            message,
+            span,
+            replacement,
+        })
+    }
+}
+
+impl Fixer for Rule {
+    fn kind(&self) -> Kind {
+        Kind {
+            id: self.name.clone().into(),
+            name: self.name.clone().into(),
+        }
+    }
+
+    fn spans<'a>(&self, message: &'a Message) -> Vec<&'a Span> {
+        let code_matches = self.codes.is_empty()
+            || message
+                .code
+                .as_ref()
+                .is_some_and(|c| self.codes.contains(&c.code));
+
+        let message_matches = self
+            .message
+            .as_ref()
+            .is_none_or(|m| m.is_match(&message.message));
+
+        if !(code_matches && message_matches) {
+            return Vec::new();
+        }
+
+        let primary = self.span == SpanChoice::Primary;
+        message
+            .spans
+            .iter()
+            .filter(|s| s.is_primary == primary)
+            .collect()
+    }
+
+    fn fix(&self, context: &Context<'_>, (start, end): (usize, usize)) -> Result<Fix> {
+        let mut replacement = String::new();
+        match &self.message {
+            Some(pattern) => match pattern.captures(&context.message.message) {
+                Some(captures) => captures.expand(&self.replacement, &mut replacement),
+                // Another message reported this code, so the template
+                // cannot be filled in
+                None => return Ok(Fix::Unrelated),
+            },
+            None => replacement.push_str(&self.replacement),
+        }
+
+        if context.content[start..end] == replacement {
+            return Ok(Fix::Unchanged);
+        }
+
+        Ok(Fix::Edit(Edit {
+            start,
+            end,
+            replacement,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scan;
+    use std::collections::BTreeMap;
+
+    const CONTENT: &str = "fn main() {\n    let _ = FooCtx.build();\n}\n";
+
+    /// A compiler message like the one for `FooCtx` in `CONTENT`.
+    fn message(text: &str, code: &str) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "message": text,
+            "level": "error",
+            "code": { "code": code },
+            "spans": [{
+                "byte_start": 24,
+                "byte_end": 30,
+                "line_start": 2,
+                "line_end": 2,
+                "column_start": 13,
+                "column_end": 19,
+                "file_name": "src/main.rs",
+                "is_primary": true,
+                "text": [{ "text": "    let _ = FooCtx.build();" }],
+                "suggested_replacement": null,
+                "suggestion_applicability": null,
+            }],
+        }))
+        .unwrap()
+    }
+
+    fn fix(rule: &Rule, message: &Message) -> Fix {
+        let scan = Scan::default();
+        let type_suffixes = BTreeMap::new();
+        let context = Context {
+            message,
+            file: Path::new("src/main.rs"),
+            content: CONTENT,
+            suffix: "Snafu",
+            type_suffixes: &type_suffixes,
+            scan: &scan,
+            suggestions: &[],
+        };
+
+        let span = &message.spans[0];
+        rule.fix(&context, (span.byte_start, span.byte_end))
+            .unwrap()
+    }
+
+    fn wrapper_rule() -> Rule {
+        let mut rules = Rule::from_toml(
+            r#"
+[[rule]]
+name = "wrapper context rename"
+codes = ["E0425"]
+message = "cannot find value `(?P<name>\\w+)Ctx`"
+replacement = "${name}Snafu"
+"#,
+        )
+        .unwrap();
+        rules.remove(0)
+    }
+
+    #[test]
+    fn fixes_matching_messages() {
+        let rule = wrapper_rule();
+        let message = message("cannot find value `FooCtx` in this scope", "E0425");
+
+        assert_eq!(rule.spans(&message), [&message.spans[0]]);
+        assert_eq!(
+            fix(&rule, &message),
+            Fix::Edit(Edit {
+                start: 24,
+                end: 30,
+                replacement: "FooSnafu".into(),
+            }),
+        );
+    }
+
+    #[test]
+    fn ignores_other_messages() {
+        let rule = wrapper_rule();
+
+        let other_code = message("cannot find value `FooCtx` in this scope", "E0433");
+        assert!(rule.spans(&other_code).is_empty());
+
+        let other_text = message("cannot find type `FooCtx` in this scope", "E0425");
+        assert!(rule.spans(&other_text).is_empty());
+    }
+
+    #[test]
+    fn does_not_write_an_unexpanded_template() {
+        let rule = wrapper_rule();
+        let other_text = message("cannot find type `FooCtx` in this scope", "E0425");
+
+        assert_eq!(fix(&rule, &other_text), Fix::Unrelated);
+    }
+
+    #[test]
+    fn leaves_fixed_code_unchanged() {
+        let mut rules = Rule::from_toml(
+            r#"
+[[rule]]
+name = "keep"
+codes = ["E0425"]
+replacement = "FooCtx"
+"#,
+        )
+        .unwrap();
+        let rule = rules.remove(0);
+        let message = message("cannot find value `FooCtx` in this scope", "E0425");
+
+        assert_eq!(fix(&rule, &message), Fix::Unchanged);
+    }
+
+    #[test]
+    fn parses_every_rule() {
+        let rules = Rule::from_toml(
+            r#"
+[[rule]]
+name = "wrapper context rename"
+codes = ["E0425"]
+message = "cannot find value `(?P<name>\\w+)Ctx`"
+span = "secondary"
+replacement = "${name}Snafu"
+
+[[rule]]
+name = "by code"
+codes = ["E0433", "E0412"]
+replacement = "Snafu"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(rules.len(), 2);
+
+        assert_eq!(rules[0].name, "wrapper context rename");
+        assert_eq!(rules[0].codes, ["E0425"]);
+        assert!(rules[0]
+            .message
+            .as_ref()
+            .unwrap()
+            .is_match("cannot find value `FooCtx`"));
+        assert_eq!(rules[0].span, SpanChoice::Secondary);
+        assert_eq!(rules[0].replacement, "${name}Snafu");
+
+        assert!(rules[1].message.is_none());
+        assert_eq!(rules[1].span, SpanChoice::Primary);
+    }
+
+    #[test]
+    fn allows_no_rules() {
+        assert!(Rule::from_toml("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn requires_codes_or_a_message() {
+        let error = Rule::from_toml(
+            r#"
+[[rule]]
+name = "matches everything"
+replacement = "Snafu"
+"#,
+        )
+        .unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .contains("`matches everything` needs error codes"),
+            "{}",
+            error,
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_message_patterns() {
+        let error = Rule::from_toml(
+            r#"
+[[rule]]
+name = "unclosed"
+message = "cannot find value `(\\w+`"
+replacement = "Snafu"
+"#,
+        )
+        .unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .contains("`unclosed` has an invalid message pattern"),
+            "{}",
+            error,
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let error = Rule::from_toml(
+            r#"
+[[rule]]
+name = "typo"
+codes = ["E0425"]
+replacment = "Snafu"
+"#,
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("replacment"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_unknown_span_choices() {
+        let result = Rule::from_toml(
+            r#"
+[[rule]]
+name = "typo"
+codes = ["E0425"]
+span = "all"
+replacement = "Snafu"
+"#,
+        );
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{manifest, Result};
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// A temporary copy of a workspace that is deleted when dropped.
+#[derive(Debug)]
+pub(crate) struct Sandbox {
+    pub(crate) original_root: PathBuf,
+    pub(crate) root: PathBuf,
+}
+
+impl Sandbox {
+    /// Copies everything inside of `original_root` except for the
+    /// paths in `skip`.
+    pub(crate) fn create(original_root: &Path, skip: &[&Path]) -> Result<Self> {
+        let root = env::temp_dir().join(format!("snafu-upgrade-assistant-{}", process::id()));
+        fs::create_dir_all(&root)?;
+
+        // Paths reported by Cargo are canonical, so ours should be as well
+        let root = fs::canonicalize(root)?;
+        let sandbox = Self {
+            original_root: original_root.to_owned(),
+            root,
+        };
+
+        copy_dir(original_root, &sandbox.root, skip)?;
+
+        Ok(sandbox)
+    }
+
+    /// Points the path dependencies in the copies of `manifests` that
+    /// are outside of the workspace at the originals, as only the
+    /// workspace is copied. Returns the original manifests that were
+    /// changed.
+    pub(crate) fn link_external_dependencies(
+        &self,
+        manifests: &BTreeSet<PathBuf>,
+    ) -> Result<BTreeSet<PathBuf>> {
+        let mut linked = BTreeSet::new();
+
+        for manifest in manifests {
+            let copy = self.to_sandbox(manifest)?;
+            let content = fs::read_to_string(&copy)?;
+            let dir = manifest.parent().unwrap_or(&self.original_root);
+
+            let modified = manifest::absolute_external_paths(&content, dir, &self.original_root)
+                .map_err(|e| format!("Could not read {}: {}", manifest.display(), e))?;
+            if let Some(modified) = modified {
+                fs::write(&copy, modified)?;
+                linked.insert(manifest.clone());
+            }
+        }
+
+        Ok(linked)
+    }
+
+    /// Converts a path inside of the original workspace to the
+    /// equivalent path inside of the sandbox.
+    pub(crate) fn to_sandbox(&self, path: &Path) -> Result<PathBuf> {
+        let relative = path.strip_prefix(&self.original_root).map_err(|_| {
+            format!(
+                "{} is not within the workspace {}",
+                path.display(),
+                self.original_root.display(),
+            )
+        })?;
+
+        Ok(self.root.join(relative))
+    }
+
+    /// Converts a path inside of the sandbox to the equivalent path
+    /// inside of the original workspace.
+    pub(crate) fn to_original(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.root) {
+            Ok(relative) => self.original_root.join(relative),
+            Err(_) => path.to_owned(),
+        }
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path, skip: &[&Path]) -> Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let from = entry.path();
+
+        if skip.contains(&&*from) || entry.file_name() == ".git" {
+            continue;
+        }
+
+        let to = to.join(entry.file_name());
+        if fs::metadata(&from)?.is_dir() {
+            copy_dir(&from, &to, skip)?;
+        } else {
+            fs::copy(&from, &to)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,691 @@
+use crate::Result;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use syn::{punctuated::Punctuated, visit::Visit, Attribute, Token};
+
+/// A type in the workspace that derives `Snafu`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorType {
+    pub name: String,
+
+    /// The path of the type within its crate, such as `db::Error`.
+    pub path: String,
+
+    /// The file containing the type, relative to the workspace root.
+    pub file: PathBuf,
+
+    /// Whether the type is declared `pub`.
+    pub is_pub: bool,
+
+    /// The byte offset of the start of the first line of the type,
+    /// including its attributes.
+    pub start_offset: usize,
+
+    /// The byte offset just after the type's `derive(Snafu)`
+    /// attribute, where other SNAFU attributes may be added.
+    pub attribute_offset: usize,
+
+    /// Whether the type is an enum. Only enums may choose their own
+    /// context selector suffix.
+    pub is_enum: bool,
+
+    /// Whether the type already has a `#[snafu(context(suffix(...)))]`
+    /// attribute.
+    pub has_suffix_attribute: bool,
+
+    /// The byte offset of the start of the line after the end of the
+    /// type.
+    pub end_offset: usize,
+
+    /// The names of the context selectors generated by SNAFU 0.6.
+    pub selectors: Vec<String>,
+
+    /// The context selectors whose generated visibility is `pub`.
+    pub public_selectors: Vec<String>,
+
+    /// How many fields each context selector has, keyed by the
+    /// selector's name. The source, backtrace, and implicit fields of
+    /// a variant are not part of its selector.
+    pub selector_fields: BTreeMap<String, usize>,
+}
+
+impl ErrorType {
+    /// The name SNAFU 0.7 generates for the context selector that
+    /// SNAFU 0.6 named `selector`, when this type's context selectors
+    /// have `suffix`. Like the variant or struct name, the name loses
+    /// any trailing `Error`, even when `suffix` is empty.
+    pub fn upgraded_selector(&self, selector: &str, suffix: &str) -> String {
+        // SNAFU 0.6 named the selector of a struct after the struct
+        let name = if self.is_enum {
+            selector
+        } else {
+            selector.strip_suffix("Context").unwrap_or(selector)
+        };
+
+        format!("{}{}", name.trim_end_matches("Error"), suffix)
+    }
+}
+
+/// Every SNAFU error type found in a workspace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scan {
+    pub error_types: Vec<ErrorType>,
+
+    /// The paths of the names introduced by renaming `use` items, such
+    /// as `error::Alpha` for `use self::AlphaSnafu as Alpha` inside of
+    /// the `error` module.
+    pub renamed_imports: BTreeSet<String>,
+
+    /// Every `pub` function, including methods.
+    pub public_functions: Vec<Function>,
+
+    /// The `src` directories of the library crates.
+    libraries: BTreeSet<PathBuf>,
+
+    /// The directories containing a `Cargo.toml`.
+    packages: BTreeSet<PathBuf>,
+
+    /// The modules of each library that are not `pub`, keyed by the
+    /// library's `src` directory.
+    private_modules: BTreeSet<(PathBuf, String)>,
+}
+
+/// A `pub` function in the workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    /// The path of the function within its crate, such as
+    /// `db::connect` or `db::Client::connect`.
+    pub path: String,
+
+    /// The file containing the function, relative to the workspace
+    /// root.
+    pub file: PathBuf,
+
+    /// The 1-based line of the function's name.
+    pub line: usize,
+
+    /// The last segment of each path mentioned by the signature, such
+    /// as the names of the argument and return types.
+    pub names: BTreeSet<String>,
+}
+
+impl Scan {
+    /// Every context selector name generated by SNAFU 0.6.
+    pub fn selector_names(&self) -> BTreeSet<&str> {
+        self.error_types
+            .iter()
+            .flat_map(|t| &t.selectors)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Whether the (possibly path-qualified) `name` refers to a
+    /// context selector generated by SNAFU 0.6.
+    pub fn is_selector(&self, name: &str) -> bool {
+        let name = name.rsplit("::").next().unwrap_or(name).trim();
+
+        self.error_types
+            .iter()
+            .any(|t| t.selectors.iter().any(|s| s == name))
+    }
+
+    /// The types that generate the (possibly path-qualified) context
+    /// selector `name` used in `file`. Types in the same package as
+    /// `file` are preferred. When `name` is qualified by a module
+    /// path, types in a matching module are preferred.
+    pub fn types_with_selector(&self, file: &Path, name: &str) -> Vec<&ErrorType> {
+        let mut segments: Vec<_> = name.split("::").map(str::trim).collect();
+        let name = segments.pop().unwrap_or_default();
+        segments.retain(|s| !matches!(*s, "" | "crate" | "self" | "super"));
+
+        let candidates: Vec<_> = self
+            .error_types
+            .iter()
+            .filter(|t| t.selectors.iter().any(|s| s == name))
+            .collect();
+
+        let package = self.package(file);
+        let in_package: Vec<_> = candidates
+            .iter()
+            .copied()
+            .filter(|t| package.is_some() && self.package(&t.file) == package)
+            .collect();
+        let candidates = if in_package.is_empty() {
+            candidates
+        } else {
+            in_package
+        };
+
+        let qualifier = segments.join("::");
+        let in_module: Vec<_> = candidates
+            .iter()
+            .copied()
+            .filter(|t| {
+                let module = t.path.rsplit_once("::").map_or("", |(m, _)| m);
+                !qualifier.is_empty()
+                    && (module == qualifier || module.ends_with(&format!("::{}", qualifier)))
+            })
+            .collect();
+
+        if in_module.is_empty() {
+            candidates
+        } else {
+            in_module
+        }
+    }
+
+    /// Whether the `pub` item at `path` in `file` can be used from
+    /// outside of its crate: it is part of a library and every module
+    /// containing it is `pub`.
+    pub fn is_reachable(&self, file: &Path, path: &str) -> bool {
+        let library = match library_dir(file) {
+            Some(library) if self.libraries.contains(&library) => library,
+            _ => return false,
+        };
+
+        let segments: Vec<_> = path.split("::").collect();
+        (1..segments.len()).all(|i| {
+            let module = segments[..i].join("::");
+            !self.private_modules.contains(&(library.clone(), module))
+        })
+    }
+
+    /// The directory of the package containing `file`.
+    fn package(&self, file: &Path) -> Option<&Path> {
+        self.packages
+            .iter()
+            .rev()
+            .find(|p| file.starts_with(p))
+            .map(PathBuf::as_path)
+    }
+
+    /// Finds the type at `path` within its crate. A leading `crate::`
+    /// is ignored.
+    pub fn error_type(&self, path: &str) -> Vec<&ErrorType> {
+        let path = path.strip_prefix("crate::").unwrap_or(path);
+        self.error_types.iter().filter(|t| t.path == path).collect()
+    }
+}
+
+/// Parses every Rust source file inside of `root`, skipping hidden
+/// directories and the paths in `skip`. Files that cannot be parsed
+/// are ignored.
+pub(crate) fn scan(root: &Path, skip: &[&Path]) -> Result<Scan> {
+    let mut scan = Scan::default();
+    scan_dir(root, root, skip, &mut scan)?;
+    Ok(scan)
+}
+
+fn scan_dir(root: &Path, dir: &Path, skip: &[&Path], scan: &mut Scan) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if hidden || skip.contains(&&*path) {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            scan_dir(root, &path, skip, scan)?;
+        } else if entry.file_name() == "Cargo.toml" {
+            let package = dir.strip_prefix(root).unwrap_or(dir);
+            scan.packages.insert(package.to_owned());
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            let content = fs::read_to_string(&path)?;
+            let file = path.strip_prefix(root).unwrap_or(&path);
+            scan_file(file, &content, scan);
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the items of the source `file`, relative to the workspace
+/// root, to `scan`.
+fn scan_file(file: &Path, content: &str, scan: &mut Scan) {
+    let parsed = match syn::parse_file(content) {
+        Ok(parsed) => parsed,
+        Err(_) => return,
+    };
+
+    if let Some(library) = library_dir(file) {
+        if file.parent() == Some(&library) && file.ends_with("lib.rs") {
+            scan.libraries.insert(library);
+        }
+    }
+
+    let mut collector = Collector {
+        file,
+        content,
+        module: module_path(file),
+        impl_type: None,
+        scan,
+    };
+    collector.visit_file(&parsed);
+}
+
+/// The module containing the items in `file`, based on Cargo's
+/// conventional source layout.
+fn module_path(file: &Path) -> Vec<String> {
+    let mut components: Vec<_> = file
+        .iter()
+        .map(|c| c.to_string_lossy().into_owned())
+        .collect();
+
+    if let Some(src) = components.iter().rposition(|c| c == "src") {
+        components.drain(..=src);
+    }
+
+    if let Some(last) = components.pop() {
+        let stem = last.strip_suffix(".rs").unwrap_or(&last);
+        if !matches!(stem, "lib" | "main" | "mod") {
+            components.push(stem.to_owned());
+        }
+    }
+
+    components
+}
+
+/// The `src` directory of the library that `file` may be part of.
+/// Binaries are not part of a library.
+fn library_dir(file: &Path) -> Option<PathBuf> {
+    let components: Vec<_> = file.iter().collect();
+    let src = components.iter().rposition(|c| *c == "src")?;
+
+    match &components[src + 1..] {
+        [name] if *name == "main.rs" => None,
+        [dir, ..] if *dir == "bin" => None,
+        _ => Some(components[..=src].iter().collect()),
+    }
+}
+
+struct Collector<'a> {
+    file: &'a Path,
+    content: &'a str,
+    module: Vec<String>,
+    /// The name of the type of the `impl` block being visited.
+    impl_type: Option<String>,
+    scan: &'a mut Scan,
+}
+
+impl Collector<'_> {
+    /// Describes the type `name` that starts and ends on the 1-based
+    /// `start_line` and `end_line`.
+    /// The type has no context selectors and is not an enum.
+    fn error_type(
+        &self,
+        name: String,
+        attrs: &[Attribute],
+        vis: &syn::Visibility,
+        start_line: usize,
+        end_line: usize,
+    ) -> ErrorType {
+        // SNAFU's attributes must come after the derive that introduces them
+        let attribute_offset =
+            snafu_derive(attrs).map_or(0, |a| self.offset(a.bracket_token.span.close().end()));
+
+        ErrorType {
+            path: self.path(&name),
+            name,
+            file: self.file.to_owned(),
+            attribute_offset,
+            is_enum: false,
+            has_suffix_attribute: has_snafu_option(attrs, "context(suffix("),
+            is_pub: is_pub(vis),
+            start_offset: self.line_offset(start_line.saturating_sub(1)),
+            end_offset: self.line_offset(end_line),
+            selectors: Vec::new(),
+            public_selectors: Vec::new(),
+            selector_fields: BTreeMap::new(),
+        }
+    }
+
+    fn path(&self, name: &str) -> String {
+        let mut path = self.module.clone();
+        path.push(name.to_owned());
+        path.join("::")
+    }
+
+    /// The byte offset of the start of the line after the 1-based
+    /// `line`.
+    fn line_offset(&self, line: usize) -> usize {
+        self.content
+            .split_inclusive('\n')
+            .take(line)
+            .map(str::len)
+            .sum()
+    }
+
+    /// The byte offset of `position`, whose column counts characters.
+    fn offset(&self, position: proc_macro2::LineColumn) -> usize {
+        let line_start = self.line_offset(position.line.saturating_sub(1));
+        let column: usize = self.content[line_start..]
+            .chars()
+            .take(position.column)
+            .map(char::len_utf8)
+            .sum();
+
+        line_start + column
+    }
+
+    fn push_function(&mut self, sig: &syn::Signature) {
+        let name = match &self.impl_type {
+            Some(impl_type) => format!("{}::{}", impl_type, sig.ident),
+            None => sig.ident.to_string(),
+        };
+
+        let mut names = PathNames::default();
+        names.visit_signature(sig);
+
+        self.scan.public_functions.push(Function {
+            path: self.path(&name),
+            file: self.file.to_owned(),
+            line: sig.ident.span().start().line,
+            names: names.0,
+        });
+    }
+
+    fn visit_use_renames(&mut self, tree: &syn::UseTree) {
+        match tree {
+            syn::UseTree::Path(p) => self.visit_use_renames(&p.tree),
+            syn::UseTree::Rename(r) => {
+                let path = self.path(&r.rename.to_string());
+                self.scan.renamed_imports.insert(path);
+            }
+            syn::UseTree::Group(g) => g.items.iter().for_each(|t| self.visit_use_renames(t)),
+            syn::UseTree::Name(_) | syn::UseTree::Glob(_) => {}
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Collector<'_> {
+    fn visit_item_enum(&mut self, item: &'ast syn::ItemEnum) {
+        if snafu_derive(&item.attrs).is_some() {
+            let enum_is_public = is_public(&item.attrs);
+            let variants: Vec<_> = item
+                .variants
+                .iter()
+                .filter(|v| !has_snafu_option(&v.attrs, "context(false)"))
+                .collect();
+
+            let selectors = variants.iter().map(|v| v.ident.to_string()).collect();
+            let public_selectors = variants
+                .iter()
+                .filter(|v| {
+                    if has_snafu_option(&v.attrs, "visibility") {
+                        is_public(&v.attrs)
+                    } else {
+                        enum_is_public
+                    }
+                })
+                .map(|v| v.ident.to_string())
+                .collect();
+
+            let selector_fields = variants
+                .iter()
+                .map(|v| {
+                    let fields = v.fields.iter().filter(|f| is_context_field(f)).count();
+                    (v.ident.to_string(), fields)
+                })
+                .collect();
+
+            let end_line = item.brace_token.span.close().end().line;
+            let error_type = ErrorType {
+                is_enum: true,
+                selectors,
+                public_selectors,
+                selector_fields,
+                ..self.error_type(
+                    item.ident.to_string(),
+                    &item.attrs,
+                    &item.vis,
+                    start_line(&item.attrs, item.enum_token.span),
+                    end_line,
+                )
+            };
+            self.scan.error_types.push(error_type);
+        }
+
+        syn::visit::visit_item_enum(self, item);
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast syn::ItemStruct) {
+        if snafu_derive(&item.attrs).is_some() {
+            let name = item.ident.to_string();
+
+            // Opaque (tuple) structs do not have a context selector,
+            // while unit structs are treated as having no named fields
+            let selectors = match &item.fields {
+                syn::Fields::Unnamed(_) => Vec::new(),
+                _ => vec![format!("{}Context", name.trim_end_matches("Error"))],
+            };
+
+            let end_line = match (&item.semi_token, &item.fields) {
+                (Some(semi), _) => semi.span.end().line,
+                (None, syn::Fields::Named(f)) => f.brace_token.span.close().end().line,
+                (None, _) => item.ident.span().end().line,
+            };
+            let error_type = ErrorType {
+                selectors,
+                ..self.error_type(
+                    name,
+                    &item.attrs,
+                    &item.vis,
+                    start_line(&item.attrs, item.struct_token.span),
+                    end_line,
+                )
+            };
+            self.scan.error_types.push(error_type);
+        }
+
+        syn::visit::visit_item_struct(self, item);
+    }
+
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        self.visit_use_renames(&item.tree);
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        if is_pub(&item.vis) {
+            self.push_function(&item.sig);
+        }
+
+        syn::visit::visit_item_fn(self, item);
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        let impl_type = match &*item.self_ty {
+            syn::Type::Path(p) => p.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        };
+
+        let outer = std::mem::replace(&mut self.impl_type, impl_type);
+        syn::visit::visit_item_impl(self, item);
+        self.impl_type = outer;
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
+        if is_pub(&item.vis) {
+            self.push_function(&item.sig);
+        }
+
+        syn::visit::visit_impl_item_fn(self, item);
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        if !is_pub(&item.vis) {
+            if let Some(library) = library_dir(self.file) {
+                let path = self.path(&item.ident.to_string());
+                self.scan.private_modules.insert((library, path));
+            }
+        }
+
+        self.module.push(item.ident.to_string());
+        syn::visit::visit_item_mod(self, item);
+        self.module.pop();
+    }
+}
+
+/// Collects the last segment of every path.
+#[derive(Default)]
+struct PathNames(BTreeSet<String>);
+
+impl<'ast> Visit<'ast> for PathNames {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if let Some(segment) = path.segments.last() {
+            self.0.insert(segment.ident.to_string());
+        }
+
+        syn::visit::visit_path(self, path);
+    }
+}
+
+fn is_pub(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Public(_))
+}
+
+/// The 1-based line that an item starts on, including its attributes.
+fn start_line(attrs: &[Attribute], keyword: proc_macro2::Span) -> usize {
+    attrs
+        .first()
+        .map_or(keyword, |a| a.pound_token.span)
+        .start()
+        .line
+}
+
+/// The `derive` attribute that includes `Snafu`, if any.
+fn snafu_derive(attrs: &[Attribute]) -> Option<&Attribute> {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("derive"))
+        .find(|a| {
+            a.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)
+                .is_ok_and(|paths| {
+                    paths
+                        .iter()
+                        .any(|p| p.segments.last().is_some_and(|s| s.ident == "Snafu"))
+                })
+        })
+}
+
+/// Whether a `#[snafu(visibility(...))]` attribute makes the context
+/// selectors `pub`.
+fn is_public(attrs: &[Attribute]) -> bool {
+    has_snafu_option(attrs, "visibility(pub)") || has_snafu_option(attrs, r#"visibility="pub""#)
+}
+
+/// Whether the field of a variant is also a field of its context
+/// selector.
+fn is_context_field(field: &syn::Field) -> bool {
+    let is = |option: &str| {
+        let named = field.ident.as_ref().is_some_and(|i| i == option);
+        let disabled = has_snafu_option(&field.attrs, &format!("{}(false)", option));
+        (named || has_snafu_option(&field.attrs, option)) && !disabled
+    };
+
+    !is("source") && !is("backtrace") && !is("implicit")
+}
+
+/// Checks for an option like `context(false)` inside of a
+/// `#[snafu(...)]` attribute.
+fn has_snafu_option(attrs: &[Attribute], option: &str) -> bool {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("snafu"))
+        .any(|a| {
+            let tokens = a.meta.require_list().map(|l| l.tokens.to_string());
+            tokens.is_ok_and(|t| {
+                t.chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect::<String>()
+                    .contains(option)
+            })
+        })
+}
+
+/// Scans the source files, given as pairs of their path relative to
+/// the workspace root and their content.
+#[cfg(test)]
+pub(crate) fn scan_sources(files: &[(&str, &str)]) -> Scan {
+    let mut scan = Scan::default();
+    for (file, content) in files {
+        scan_file(Path::new(file), content, &mut scan);
+    }
+    scan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_and_unit_structs_have_a_context_selector() {
+        let scan = scan_sources(&[(
+            "src/lib.rs",
+            r#"
+#[derive(Debug, Snafu)]
+struct ConfigError {
+    source: std::io::Error,
+}
+
+#[derive(Debug, Snafu)]
+struct Empty;
+"#,
+        )]);
+
+        assert_eq!(scan.error_types[0].selectors, ["ConfigContext"]);
+        assert_eq!(scan.error_types[1].selectors, ["EmptyContext"]);
+    }
+
+    #[test]
+    fn opaque_structs_have_no_context_selector() {
+        let scan = scan_sources(&[(
+            "src/lib.rs",
+            r#"
+#[derive(Debug, Snafu)]
+pub struct Error(InnerError);
+"#,
+        )]);
+
+        assert_eq!(scan.error_types.len(), 1);
+        assert!(scan.selector_names().is_empty());
+        assert!(!scan.is_selector("Context"));
+    }
+
+    #[test]
+    fn items_in_pub_modules_of_libraries_are_reachable() {
+        let scan = scan_sources(&[
+            (
+                "src/lib.rs",
+                "pub mod error { pub mod io {} }\nmod private { pub mod nested {} }\n",
+            ),
+            ("src/error.rs", "pub struct Error;\n"),
+        ]);
+
+        let lib = Path::new("src/lib.rs");
+        assert!(scan.is_reachable(lib, "Error"));
+        assert!(scan.is_reachable(lib, "error::Error"));
+        assert!(scan.is_reachable(lib, "error::io::Error"));
+        assert!(scan.is_reachable(Path::new("src/error.rs"), "error::Error"));
+
+        assert!(!scan.is_reachable(lib, "private::Error"));
+        assert!(!scan.is_reachable(lib, "private::nested::Error"));
+    }
+
+    #[test]
+    fn items_outside_of_libraries_are_not_reachable() {
+        let scan = scan_sources(&[
+            ("app/src/main.rs", "pub mod error {}\n"),
+            ("app/src/bin/tool.rs", "pub mod error {}\n"),
+            ("lib/src/lib.rs", "pub mod error {}\n"),
+        ]);
+
+        assert!(!scan.is_reachable(Path::new("app/src/main.rs"), "error::Error"));
+        assert!(!scan.is_reachable(Path::new("app/src/bin/tool.rs"), "error::Error"));
+        assert!(scan.is_reachable(Path::new("lib/src/lib.rs"), "error::Error"));
+    }
+}
@@ -0,0 +1,454 @@
+use crate::{edit::line_column, Category, Edit, ErrorType, Kind, Migration, Scan};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// A change to the public API of a library crate.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ApiChange {
+    /// A `pub` context selector of a reachable error type was renamed.
+    SelectorRenamed {
+        file: PathBuf,
+        old: String,
+        new: String,
+        /// A deprecated alias or a re-export keeps the old name working.
+        aliased: bool,
+    },
+
+    /// A reachable `pub` function mentions a renamed context selector
+    /// in its signature.
+    SignatureChanged {
+        file: PathBuf,
+        line: usize,
+        function: String,
+        selector: String,
+        /// A deprecated alias or a re-export keeps the old name working.
+        aliased: bool,
+    },
+
+    /// The `visibility` attribute of a reachable `pub` error type was
+    /// rewritten.
+    VisibilityRewritten {
+        file: PathBuf,
+        line: usize,
+        error_type: String,
+        original: String,
+        replacement: String,
+    },
+
+    /// The definition of a reachable `pub` error type was changed.
+    ErrorTypeChanged {
+        file: PathBuf,
+        error_type: String,
+        /// The name of each kind of fix that changed the type.
+        fixes: BTreeSet<String>,
+    },
+}
+
+impl ApiChange {
+    /// Whether code outside of the crate that used the old API may no
+    /// longer compile.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            ApiChange::SelectorRenamed { aliased, .. } => !aliased,
+            ApiChange::SignatureChanged { aliased, .. } => !aliased,
+            ApiChange::VisibilityRewritten { .. } => false,
+            ApiChange::ErrorTypeChanged { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for ApiChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiChange::SelectorRenamed {
+                file,
+                old,
+                new,
+                aliased,
+            } => {
+                write!(
+                    f,
+                    "{}: the context selector `{}` was renamed to `{}`",
+                    file.display(),
+                    old,
+                    new
+                )?;
+                if *aliased {
+                    write!(f, "; the old name is still available")?;
+                }
+                Ok(())
+            }
+            ApiChange::SignatureChanged {
+                file,
+                line,
+                function,
+                selector,
+                aliased,
+            } => {
+                write!(
+                    f,
+                    "{}:{}: the signature of `{}` mentions the renamed context selector `{}`",
+                    file.display(),
+                    line,
+                    function,
+                    selector
+                )?;
+                if *aliased {
+                    write!(f, "; the old name is still available")?;
+                }
+                Ok(())
+            }
+            ApiChange::VisibilityRewritten {
+                file,
+                line,
+                error_type,
+                original,
+                replacement,
+            } => write!(
+                f,
+                "{}:{}: the visibility of the context selectors of `{}` was rewritten from `{}` to `{}`",
+                file.display(),
+                line,
+                error_type,
+                original,
+                replacement
+            ),
+            ApiChange::ErrorTypeChanged {
+                file,
+                error_type,
+                fixes,
+            } => {
+                let fixes: Vec<_> = fixes.iter().map(String::as_str).collect();
+                write!(
+                    f,
+                    "{}: the error type `{}` was changed ({})",
+                    file.display(),
+                    error_type,
+                    fixes.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Whether a [`Migration`] changed the public API of a library crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemverImpact<'a> {
+    /// Every change to the public API, without duplicates.
+    pub changes: Vec<&'a ApiChange>,
+}
+
+impl SemverImpact<'_> {
+    /// Whether a new major version should be released.
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(|c| c.is_breaking())
+    }
+}
+
+impl fmt::Display for SemverImpact<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "No changes to the public API were found");
+        }
+
+        writeln!(f, "Public API changes:")?;
+        for change in &self.changes {
+            let label = if change.is_breaking() {
+                "breaking"
+            } else {
+                "compatible"
+            };
+            writeln!(f, "  [{}] {}", label, change)?;
+        }
+
+        if self.is_breaking() {
+            writeln!(f, "The public API changed; release a new major version")
+        } else {
+            writeln!(f, "The public API is compatible; a minor release is enough")
+        }
+    }
+}
+
+impl Migration {
+    /// Describes how the iterations changed the public API of the
+    /// library crates in the workspace.
+    pub fn semver_impact(&self) -> SemverImpact<'_> {
+        let changes: BTreeSet<_> = self
+            .iterations
+            .iter()
+            .flat_map(|i| &i.api_changes)
+            .collect();
+
+        SemverImpact {
+            changes: changes.into_iter().collect(),
+        }
+    }
+}
+
+/// A reachable `pub` error type and the suffix its context selectors
+/// were given.
+#[derive(Debug)]
+pub(crate) struct Renamed<'a> {
+    pub(crate) error_type: &'a ErrorType,
+    pub(crate) suffix: &'a str,
+}
+
+/// Finds the changes to the public API made by one iteration.
+/// `renamed` are the error types whose context selectors were renamed
+/// by the iteration, and `edits` are the applied edits for each file
+/// relative to the workspace root, along with the file's content
+/// before the edits.
+pub(crate) fn api_changes(
+    scan: &Scan,
+    renamed: &[Renamed<'_>],
+    edits: &BTreeMap<&Path, (&str, &[Category<Edit>])>,
+) -> Vec<ApiChange> {
+    let mut changes = Vec::new();
+
+    let is_reachable = |t: &ErrorType| t.is_enum && t.is_pub && scan.is_reachable(&t.file, &t.path);
+
+    let mut renamed_selectors = Vec::new();
+    for &Renamed { error_type, suffix } in renamed {
+        if !is_reachable(error_type) {
+            continue;
+        }
+
+        let (_, file_edits) = edits
+            .get(error_type.file.as_path())
+            .copied()
+            .unwrap_or_default();
+        let aliased = file_edits
+            .iter()
+            .any(|e| e.kind == Kind::SELECTOR_ALIAS && e.value.start == error_type.end_offset);
+        let module = error_type.path.rsplit_once("::").map(|(m, _)| m);
+
+        for old in &error_type.public_selectors {
+            let new = error_type.upgraded_selector(old, suffix);
+            let qualify =
+                |name: &str| module.map_or_else(|| name.to_owned(), |m| format!("{}::{}", m, name));
+            let aliased = aliased || scan.renamed_imports.contains(&qualify(old));
+
+            changes.push(ApiChange::SelectorRenamed {
+                file: error_type.file.clone(),
+                old: qualify(old),
+                new: qualify(&new),
+                aliased,
+            });
+            renamed_selectors.push((old, new, aliased));
+        }
+    }
+
+    for function in &scan.public_functions {
+        if !scan.is_reachable(&function.file, &function.path) {
+            continue;
+        }
+
+        for (old, new, aliased) in &renamed_selectors {
+            if function.names.contains(*old) || function.names.contains(new) {
+                changes.push(ApiChange::SignatureChanged {
+                    file: function.file.clone(),
+                    line: function.line,
+                    function: function.path.clone(),
+                    selector: old.to_string(),
+                    aliased: *aliased,
+                });
+            }
+        }
+    }
+
+    for error_type in scan.error_types.iter().filter(|t| is_reachable(t)) {
+        let (content, file_edits) = edits
+            .get(error_type.file.as_path())
+            .copied()
+            .unwrap_or_default();
+        let range = error_type.start_offset..error_type.end_offset;
+        let type_edits = file_edits.iter().filter(|e| range.contains(&e.value.start));
+        let mut fixes = BTreeSet::new();
+
+        for edit in type_edits {
+            fixes.insert(edit.name().to_owned());
+
+            let (line, _) = line_column(content, edit.value.start);
+            let line_text = content.lines().nth(line - 1).unwrap_or_default();
+            if edit.kind != Kind::SUFFIX_ATTRIBUTE && line_text.contains("visibility") {
+                changes.push(ApiChange::VisibilityRewritten {
+                    file: error_type.file.clone(),
+                    line,
+                    error_type: error_type.path.clone(),
+                    original: edit.value.original(content).trim().to_owned(),
+                    replacement: edit.value.replacement.trim().to_owned(),
+                });
+            }
+        }
+
+        if !fixes.is_empty() {
+            changes.push(ApiChange::ErrorTypeChanged {
+                file: error_type.file.clone(),
+                error_type: error_type.path.clone(),
+                fixes,
+            });
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scan::scan_sources, EqualSyntax, Fixer};
+
+    const LIB: &str = r#"pub mod error {
+    use snafu::Snafu;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility = "pub")]
+    pub enum Error {
+        Alpha { path: String },
+    }
+}
+
+mod private {
+    use snafu::Snafu;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub))]
+    pub enum Error {
+        Beta,
+    }
+}
+
+pub fn alpha(path: &str) -> error::Alpha<&str> {
+    error::Alpha { path }
+}
+
+pub fn beta() -> private::Beta {
+    private::Beta
+}
+"#;
+
+    fn renamed(scan: &Scan) -> Vec<Renamed<'_>> {
+        scan.error_types
+            .iter()
+            .map(|error_type| Renamed {
+                error_type,
+                suffix: "Snafu",
+            })
+            .collect()
+    }
+
+    fn changes(scan: &Scan, edits: &[Category<Edit>]) -> Vec<ApiChange> {
+        let mut file_edits = BTreeMap::new();
+        file_edits.insert(Path::new("src/lib.rs"), (LIB, edits));
+
+        api_changes(scan, &renamed(scan), &file_edits)
+    }
+
+    #[test]
+    fn renames_in_reachable_modules_are_breaking() {
+        let scan = scan_sources(&[("src/lib.rs", LIB)]);
+
+        let changes = changes(&scan, &[]);
+
+        assert_eq!(
+            changes,
+            [
+                ApiChange::SelectorRenamed {
+                    file: "src/lib.rs".into(),
+                    old: "error::Alpha".into(),
+                    new: "error::AlphaSnafu".into(),
+                    aliased: false,
+                },
+                ApiChange::SignatureChanged {
+                    file: "src/lib.rs".into(),
+                    line: 21,
+                    function: "alpha".into(),
+                    selector: "Alpha".into(),
+                    aliased: false,
+                },
+            ],
+        );
+        assert!(changes.iter().all(ApiChange::is_breaking));
+    }
+
+    #[test]
+    fn renames_in_binaries_are_not_part_of_the_api() {
+        let scan = scan_sources(&[("src/main.rs", LIB)]);
+
+        assert!(changes(&scan, &[]).is_empty());
+    }
+
+    #[test]
+    fn deprecated_aliases_keep_renames_compatible() {
+        let scan = scan_sources(&[("src/lib.rs", LIB)]);
+        let alias = Category::new(
+            Kind::SELECTOR_ALIAS,
+            Edit {
+                start: scan.error_types[0].end_offset,
+                end: scan.error_types[0].end_offset,
+                replacement: "pub type Alpha<__T0> = AlphaSnafu<__T0>;\n".into(),
+            },
+        );
+
+        let changes = changes(&scan, &[alias]);
+
+        assert!(changes.iter().all(|c| !c.is_breaking()), "{:?}", changes);
+        assert!(changes.contains(&ApiChange::SelectorRenamed {
+            file: "src/lib.rs".into(),
+            old: "error::Alpha".into(),
+            new: "error::AlphaSnafu".into(),
+            aliased: true,
+        }));
+    }
+
+    #[test]
+    fn renamed_imports_keep_renames_compatible() {
+        let lib = LIB.replacen(
+            "pub mod error {\n",
+            "pub mod error {\n    pub use self::AlphaSnafu as Alpha;\n",
+            1,
+        );
+        let scan = scan_sources(&[("src/lib.rs", &lib)]);
+
+        let changes = api_changes(&scan, &renamed(&scan), &BTreeMap::new());
+
+        assert!(!changes.is_empty());
+        assert!(changes.iter().all(|c| !c.is_breaking()), "{:?}", changes);
+    }
+
+    #[test]
+    fn rewritten_visibility_is_reported() {
+        let scan = scan_sources(&[("src/lib.rs", LIB)]);
+        let original = r#"visibility = "pub""#;
+        let start = LIB.find(original).unwrap();
+        let rewrite = Category::new(
+            EqualSyntax.kind(),
+            Edit {
+                start,
+                end: start + original.len(),
+                replacement: "visibility(pub)".into(),
+            },
+        );
+
+        let changes = changes(&scan, &[rewrite]);
+
+        assert!(changes.contains(&ApiChange::VisibilityRewritten {
+            file: "src/lib.rs".into(),
+            line: 5,
+            error_type: "error::Error".into(),
+            original: original.into(),
+            replacement: "visibility(pub)".into(),
+        }));
+        assert!(changes.contains(&ApiChange::ErrorTypeChanged {
+            file: "src/lib.rs".into(),
+            error_type: "error::Error".into(),
+            fixes: Some("attribute syntax".to_owned()).into_iter().collect(),
+        }));
+    }
+}
@@ -0,0 +1,17 @@
+use snafu::{ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+enum Error {
+    Alpha,
+    BetaError { source: std::io::Error },
+}
+
+#[derive(Debug, Snafu)] enum OtherError {
+    Gamma,
+}
+
+fn main() {
+    let _ = Alpha.build();
+    let _ = std::fs::read("config.toml").context(BetaError);
+    let _ = Gamma.build();
+}
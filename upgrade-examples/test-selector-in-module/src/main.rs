@@ -0,0 +1,15 @@
+mod error {
+    use snafu::Snafu;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(crate)))]
+    pub enum Error {
+        Alpha,
+        #[snafu(context(false))]
+        Beta { source: std::io::Error },
+    }
+}
+
+fn main() {
+    let _ = error::Alpha.build();
+}
@@ -0,0 +1,11 @@
+use snafu::{ResultExt, Snafu};
+
+#[derive(Debug, Snafu)] enum ConfigError {
+    Alpha,
+    Beta { source: std::io::Error },
+}
+
+fn main() {
+    let _ = Alpha.build();
+    let _ = std::fs::read("config.toml").context(Beta);
+}
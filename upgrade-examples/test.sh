@@ -3,14 +3,20 @@
 set -eu
 
 function run_one_in_place() (
-    trap 'git checkout -- . ../Cargo.lock' EXIT
+    # Upgrading the dependency changes every member of the workspace
+    trap 'git checkout -- ..' EXIT
 
-    cargo run --quiet --manifest-path=../../Cargo.toml
-    sed -e 's/0\.6/0.7.0-beta.2/' Cargo.toml > Cargo.post.toml
+    # Some examples need extra options, listed in their `args` file
+    local args=()
+    if [[ -f args ]]; then
+        read -ra args < args
+    fi
 
-    mv Cargo.post.toml Cargo.toml
-    cargo update --quiet
-    cargo run --quiet --manifest-path=../../Cargo.toml
+    # The examples are changed in place and restored afterwards
+    args+=(--allow-dirty)
+
+    cargo run --quiet --manifest-path=../../Cargo.toml -- "${args[@]}"
+    cargo run --quiet --manifest-path=../../Cargo.toml -- --upgrade-dependency "${args[@]}"
 
     # Successful build?
     cargo build --quiet